@@ -1,4 +1,5 @@
 //! Describes the expression AST nodes.
+use std::rc::Rc;
 use crate::ast::token::Token;
 
 #[derive(Debug, Clone)]
@@ -8,19 +9,26 @@ pub enum Expr {
     Grouping(Box<Expr>),
     Literal(LiteralValue),
     Unary(Token, Box<Expr>),
-    Assign(Token, Box<Expr>),
-    Variable(Token),
-    Call(Box<Expr>, Vec<Expr>, usize), // (callee, arguments, line)
+    Assign(Token, Box<Expr>, NodeId), // (name, value, id)
+    Variable(Token, NodeId), // (name, id)
+    Call(Box<Expr>, Vec<Expr>, usize, NodeId), // (callee, arguments, line, id)
     Get(Box<Expr>, Token), // (object, name)
     Set(Box<Expr>, Token, Box<Expr>), // (object, name, value)
-    This(Token), 
-    Super(Token, Token), // (keyword, method)
+    This(Token, NodeId), // (keyword, id)
+    Super(Token, Token, NodeId), // (keyword, method, id)
+    Interpolation(Vec<Expr>), // string parts and embedded expressions, in order
 }
 
+/// Identifies a node that names a variable or makes a call, so that what the resolver
+/// binds it to, or the method a call last found, can be kept in side tables. The
+/// parser numbers these nodes as it meets them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(pub usize);
+
 #[derive(Debug, Clone)]
 pub enum LiteralValue {
     Number(f64),
-    String(String),
+    String(Rc<str>),
     Boolean(bool),
     Nil,
 }
@@ -31,13 +39,14 @@ pub trait Visitor<T> {
     fn visit_grouping_expr(&mut self, expression: &Expr) -> T;
     fn visit_literal_expr(&mut self, value: &LiteralValue) -> T;
     fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> T;
-    fn visit_variable_expr(&mut self, name: &Token) -> T;
-    fn visit_assign_expr(&mut self, left: &Token, right: &Expr) -> T;
-    fn visit_call_expr(&mut self, callee: &Expr, arguments: &[Expr]) -> T;
+    fn visit_variable_expr(&mut self, name: &Token, id: NodeId) -> T;
+    fn visit_assign_expr(&mut self, left: &Token, right: &Expr, id: NodeId) -> T;
+    fn visit_call_expr(&mut self, callee: &Expr, arguments: &[Expr], id: NodeId) -> T;
     fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> T;
     fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> T;
-    fn visit_this_expr(&mut self, name: &Token) -> T;
-    fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> T;
+    fn visit_this_expr(&mut self, name: &Token, id: NodeId) -> T;
+    fn visit_super_expr(&mut self, keyword: &Token, method: &Token, id: NodeId) -> T;
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) -> T;
 }
 
 impl Expr {
@@ -55,20 +64,22 @@ impl Expr {
                 => visitor.visit_literal_expr(value),
             Expr::Unary(operator, right) 
                 => visitor.visit_unary_expr(operator, right),
-            Expr::Variable(name)
-                => visitor.visit_variable_expr(name),
-            Expr::Assign(left, right)
-                => visitor.visit_assign_expr(left, right),
-            Expr::Call(callee, arguments, _)
-                => visitor.visit_call_expr(callee, arguments),
+            Expr::Variable(name, id)
+                => visitor.visit_variable_expr(name, *id),
+            Expr::Assign(left, right, id)
+                => visitor.visit_assign_expr(left, right, *id),
+            Expr::Call(callee, arguments, _, id)
+                => visitor.visit_call_expr(callee, arguments, *id),
             Expr::Get(object, name)
                 => visitor.visit_get_expr(object, name),
             Expr::Set(object, name, value)
                 => visitor.visit_set_expr(object, name, value),
-            Expr::This(name)
-                => visitor.visit_this_expr(name),
-            Expr::Super(keyword, method)
-                => visitor.visit_super_expr(keyword, method),
+            Expr::This(name, id)
+                => visitor.visit_this_expr(name, *id),
+            Expr::Super(keyword, method, id)
+                => visitor.visit_super_expr(keyword, method, *id),
+            Expr::Interpolation(parts)
+                => visitor.visit_interpolation_expr(parts),
         }
     }
 }
\ No newline at end of file
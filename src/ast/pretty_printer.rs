@@ -50,7 +50,7 @@ impl expr::Visitor<String> for AstPrinter {
     fn visit_literal_expr(&mut self, value: &expr::LiteralValue) -> String {
         return match value {
             expr::LiteralValue::Number(n) => n.to_string(),
-            expr::LiteralValue::String(s) => s.clone(),
+            expr::LiteralValue::String(s) => s.to_string(),
             expr::LiteralValue::Boolean(b) => b.to_string(),
             expr::LiteralValue::Nil => "nil".to_string(),
         }
@@ -60,11 +60,11 @@ impl expr::Visitor<String> for AstPrinter {
         return self.parenthesize(&operator.lexeme, vec![right]);
     }
 
-    fn visit_variable_expr(&mut self, name: &token::Token) -> String {
+    fn visit_variable_expr(&mut self, name: &token::Token, _id: expr::NodeId) -> String {
         return name.lexeme.clone();
     }
 
-    fn visit_assign_expr(&mut self, left: &token::Token, right: &expr::Expr) -> String {
+    fn visit_assign_expr(&mut self, left: &token::Token, right: &expr::Expr, _id: expr::NodeId) -> String {
         let mut result = String::new();
         result.push_str("(= ");
         result.push_str(&left.lexeme);
@@ -74,7 +74,7 @@ impl expr::Visitor<String> for AstPrinter {
         result
     }
 
-    fn visit_call_expr(&mut self, callee: &expr::Expr, arguments: &[expr::Expr]) -> String {
+    fn visit_call_expr(&mut self, callee: &expr::Expr, arguments: &[expr::Expr], _id: expr::NodeId) -> String {
         let mut result = String::new();
         result.push_str("(call ");
         result.push_str(&callee.accept(self));
@@ -108,11 +108,11 @@ impl expr::Visitor<String> for AstPrinter {
         result
     }
     
-    fn visit_this_expr(&mut self, name: &token::Token) -> String {
+    fn visit_this_expr(&mut self, name: &token::Token, _id: expr::NodeId) -> String {
         return name.lexeme.clone();
     }
 
-    fn visit_super_expr(&mut self, keyword: &token::Token, method: &token::Token) -> String {
+    fn visit_super_expr(&mut self, keyword: &token::Token, method: &token::Token, _id: expr::NodeId) -> String {
         let mut result = String::new();
         result.push_str("(super ");
         result.push_str(&keyword.lexeme);
@@ -121,6 +121,17 @@ impl expr::Visitor<String> for AstPrinter {
         result.push_str(")");
         result
     }
+
+    fn visit_interpolation_expr(&mut self, parts: &[expr::Expr]) -> String {
+        let mut result = String::new();
+        result.push_str("(interpolate");
+        for part in parts {
+            result.push(' ');
+            result.push_str(&part.accept(self));
+        }
+        result.push(')');
+        result
+    }
 }
 
 impl stmt::Visitor<String> for AstPrinter {
@@ -152,7 +163,7 @@ impl stmt::Visitor<String> for AstPrinter {
         result
     }
 
-    fn visit_if_stmt(&mut self, condition: &expr::Expr, then_branch: &Box<stmt::Stmt>, else_branch: &Option<Box<stmt::Stmt>>) -> String {
+    fn visit_if_stmt(&mut self, _keyword: &token::Token, condition: &expr::Expr, then_branch: &Box<stmt::Stmt>, else_branch: &Option<Box<stmt::Stmt>>) -> String {
         let mut result = String::new();
         result.push_str("(if ");
         result.push_str(&condition.accept(self));
@@ -166,7 +177,7 @@ impl stmt::Visitor<String> for AstPrinter {
         result
     }
 
-    fn visit_while_stmt(&mut self, condition: &expr::Expr, body: &Box<stmt::Stmt>) -> String {
+    fn visit_while_stmt(&mut self, _keyword: &token::Token, condition: &expr::Expr, body: &Box<stmt::Stmt>) -> String {
         let mut result = String::new();
         result.push_str("(while ");
         result.push_str(&condition.accept(self));
@@ -191,7 +202,7 @@ impl stmt::Visitor<String> for AstPrinter {
         result
     }
 
-    fn visit_return_stmt(&mut self, value: &Option<expr::Expr>) -> String {
+    fn visit_return_stmt(&mut self, _keyword: &token::Token, value: &Option<expr::Expr>) -> String {
         let mut result = String::new();
         result.push_str("(return");
         if let Some(expr) = value {
@@ -218,5 +229,14 @@ impl stmt::Visitor<String> for AstPrinter {
         result
     }
 
+    fn visit_test_stmt(&mut self, _keyword: &token::Token, name: &str, body: &Rc<Vec<stmt::Stmt>>) -> String {
+        let mut result = String::new();
+        result.push_str("(test \"");
+        result.push_str(name);
+        result.push_str("\" ");
+        result.push_str(&self.block(body));
+        result.push(')');
+        result
+    }
 }
 
@@ -6,16 +6,25 @@ use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum Stmt {
-    Var(Token, Option<Expr>),
+    Var(Token, Option<Expr>, Option<Token>),  // name, initializer, type annotation
     Block(Vec<Stmt>),
     Program(Vec<Stmt>),
-    Expression(Expr),
-    Print(Expr),
-    Return(Option<Expr>),
-    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
-    While(Expr, Box<Stmt>),
-    FunctionDecl(Token, Vec<Token>, Rc<Vec<Stmt>>),   // Decl name, params, body. Body uses Rc, because function instance will link to it.
-    ClassDecl(Token, Option<Expr>, Vec<Stmt>), // Class name, super class, methods (FuntionDecl)
+    Expression(Expr, usize),    // expression, line
+    Print(Expr, usize),         // expression, line
+    Return(Token, Option<Expr>),  // keyword, value
+    If(Expr, Box<Stmt>, Option<Box<Stmt>>, Token),  // condition, then, else, `if` keyword
+    While(Expr, Box<Stmt>, Token),  // condition, body, `while` (or `for`) keyword
+    FunctionDecl(Token, Vec<Token>, Rc<Vec<Stmt>>, Option<String>, Signature),   // Decl name, params, body, doc comment, type annotations. Body uses Rc, because function instance will link to it.
+    ClassDecl(Token, Option<Expr>, Vec<Stmt>, Option<String>), // Class name, super class, methods (FuntionDecl), doc comment
+    Test(Token, String, Rc<Vec<Stmt>>),     // `test` keyword, test name, body
+}
+
+/// The type annotations of a function, one per parameter and one for its result.
+/// Each annotation is the name of a type, and missing ones are `None`.
+#[derive(Debug, Clone, Default)]
+pub struct Signature {
+    pub params: Vec<Option<Token>>,
+    pub returns: Option<Token>,
 }
 
 pub trait Visitor<T> {
@@ -24,11 +33,12 @@ pub trait Visitor<T> {
     fn visit_expression_stmt(&mut self, expression: &Expr) -> T;
     fn visit_print_stmt(&mut self, expression: &Expr) -> T;
     fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) -> T;
-    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Box<Stmt>, else_branch: &Option<Box<Stmt>>) -> T;
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Box<Stmt>) -> T;
+    fn visit_if_stmt(&mut self, keyword: &Token, condition: &Expr, then_branch: &Box<Stmt>, else_branch: &Option<Box<Stmt>>) -> T;
+    fn visit_while_stmt(&mut self, keyword: &Token, condition: &Expr, body: &Box<Stmt>) -> T;
     fn visit_function_decl_stmt(&mut self, name: &Token, params: &Vec<Token>, body: &Rc<Vec<Stmt>>) -> T;
-    fn visit_return_stmt(&mut self, value: &Option<Expr>) -> T;
+    fn visit_return_stmt(&mut self, keyword: &Token, value: &Option<Expr>) -> T;
     fn visit_class_decl_stmt(&mut self, name: &Token, super_class: &Option<Expr>, methods: &Vec<Stmt>) -> T;
+    fn visit_test_stmt(&mut self, keyword: &Token, name: &str, body: &Rc<Vec<Stmt>>) -> T;
 }
 
 impl Stmt {
@@ -40,23 +50,48 @@ impl Stmt {
                 => visitor.visit_program_stmt(declarations),
             Stmt::Block(declarations) 
                 => visitor.visit_block_stmt(declarations),
-            Stmt::Expression(expression) 
+            Stmt::Expression(expression, _) 
                 => visitor.visit_expression_stmt(expression),
-            Stmt::Print(expression) 
+            Stmt::Print(expression, _) 
                 => visitor.visit_print_stmt(expression),
-            Stmt::Var(name, initializer) => 
+            Stmt::Var(name, initializer, _) => 
                 visitor.visit_var_stmt(name, initializer),
-            Stmt::If(condition, then_branch, else_branch)
-                => visitor.visit_if_stmt(condition, then_branch, else_branch),
-            Stmt::While(condition, body)
-                => visitor.visit_while_stmt(condition, body),
-            Stmt::FunctionDecl(name, params, body)
+            Stmt::If(condition, then_branch, else_branch, keyword)
+                => visitor.visit_if_stmt(keyword, condition, then_branch, else_branch),
+            Stmt::While(condition, body, keyword)
+                => visitor.visit_while_stmt(keyword, condition, body),
+            Stmt::FunctionDecl(name, params, body, _, _)
                 => visitor.visit_function_decl_stmt(name, params, body),
-            Stmt::Return(value)
-                => visitor.visit_return_stmt(value),
-            Stmt::ClassDecl(name,super_class, methods)
+            Stmt::Return(keyword, value)
+                => visitor.visit_return_stmt(keyword, value),
+            Stmt::ClassDecl(name, super_class, methods, _)
                 => visitor.visit_class_decl_stmt(name, super_class, methods),
+            Stmt::Test(keyword, name, body)
+                => visitor.visit_test_stmt(keyword, name, body),
+
+        }
+    }
 
+    /// The source line a statement starts at. Blocks and programs have no line of their own.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Stmt::Var(name, _, _) => Some(name.line),
+            Stmt::Expression(_, line)
+            | Stmt::Print(_, line) => Some(*line),
+            Stmt::Return(keyword, _)
+            | Stmt::Test(keyword, _, _)
+            | Stmt::If(_, _, _, keyword)
+            | Stmt::While(_, _, keyword) => Some(keyword.line),
+            Stmt::FunctionDecl(name, ..) | Stmt::ClassDecl(name, ..) => Some(name.line),
+            Stmt::Block(_) | Stmt::Program(_) => None,
         }
     }
-}
\ No newline at end of file
+
+    /// The `///` comment written before a function, method or class declaration.
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Stmt::FunctionDecl(_, _, _, doc, _) | Stmt::ClassDecl(_, _, _, doc) => doc.as_deref(),
+            _ => None,
+        }
+    }
+}
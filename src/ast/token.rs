@@ -2,7 +2,7 @@
 pub enum TokenType {
     // Single-character tokens
     LeftParen, RightParen, LeftBrace, RightBrace,
-    Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
+    Comma, Dot, Colon, Minus, Plus, Semicolon, Slash, Star, Percent,
 
     // One or two character tokens
     Bang, BangEqual,
@@ -11,7 +11,13 @@ pub enum TokenType {
     Less, LessEqual,
 
     // Literals
-    Identifier, String, Number,
+    Identifier, String, StringPart, Number,
+
+    // `///` comments, which the parser attaches to the declaration after them
+    DocComment,
+
+    // Trivia, only kept if the scanner is asked to. Invalid is text that failed to scan.
+    Whitespace, Comment, Invalid,
 
     // Keywords
     And, Class, Else, False, Fun, For, If, Nil, Or,
@@ -25,21 +31,21 @@ pub struct Token {
     pub t_type: TokenType,
     pub lexeme: String,
     pub line: usize,
-    pub id: usize,
+    pub column: usize,
 }
 
-static mut ID_COUNTER: usize = 0;
-
 impl Token {
     pub fn new(t_type: TokenType, lexeme: String, line: usize) -> Token {
+        Token::new_at(t_type, lexeme, line, 0)
+    }
+
+    /// Creates a token that also records its (1-based) column in the source line.
+    pub fn new_at(t_type: TokenType, lexeme: String, line: usize, column: usize) -> Token {
         Token {
             t_type,
             lexeme,
             line,
-            id: unsafe {
-                ID_COUNTER += 1;
-                ID_COUNTER
-            },
+            column,
         }
     }
 }
\ No newline at end of file
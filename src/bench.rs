@@ -0,0 +1,203 @@
+//! Benchmarks:
+//! Runs the Lox scripts in a directory in-process, timing them and counting
+//! the heap allocations they make, and compares the results with a baseline
+//! saved by an earlier run.
+//!
+//! Allocations are counted by `CountingAllocator`, which the binary installs
+//! as its global allocator when built with the `bench` feature. Without it
+//! every count is 0.
+//!
+//! A benchmark regresses when its best time, or its allocation count, is more
+//! than the threshold above the baseline. Times vary from run to run, so the
+//! best of several runs is kept.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Map, Value};
+
+use crate::error::RloxError;
+use crate::interpreter::Interpreter;
+use crate::runner::{self, Options};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting the allocations made through it.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// The number of allocations made so far, if `CountingAllocator` is installed.
+pub fn allocations() -> usize {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+/// How `rlox bench` runs, chosen on the command line.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// How many times each benchmark runs. The best time is kept.
+    pub runs: usize,
+    /// Results to compare against.
+    pub baseline: Option<PathBuf>,
+    /// Where to save the results, to serve as a later baseline.
+    pub save: Option<PathBuf>,
+    /// How much slower, in percent, a benchmark may get before it counts as a regression.
+    pub threshold: f64,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        BenchOptions { runs: 3, baseline: None, save: None, threshold: 10.0 }
+    }
+}
+
+/// The result of one benchmark.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub name: String,
+    pub time: Duration,
+    pub allocations: usize,
+}
+
+/// Runs the script at `path` `runs` times, discarding what it prints.
+/// Fails if the script does not compile or stops with a runtime error.
+pub fn measure(path: &Path, runs: usize) -> Result<Measurement, RloxError> {
+    let source = fs::read_to_string(path)?;
+    let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let mut best: Option<Measurement> = None;
+    for _ in 0..runs.max(1) {
+        let before = allocations();
+        let start = Instant::now();
+        let mut interpreter = Interpreter::new();
+        interpreter.output = Box::new(io::sink());
+        let Ok(program) = runner::compile(source.clone(), &mut interpreter, &Options::default()) else {
+            return Err(RloxError::RuntimeError(format!("{} does not compile.", path.display())));
+        };
+        interpreter.interpret(program);
+        let time = start.elapsed();
+        if interpreter.had_error {
+            return Err(RloxError::RuntimeError(format!("{} stopped with a runtime error.", path.display())));
+        }
+        let run = Measurement { name: name.clone(), time, allocations: allocations() - before };
+        if best.as_ref().is_none_or(|best| run.time < best.time) {
+            best = Some(run);
+        }
+    }
+    Ok(best.unwrap())
+}
+
+/// Reads results saved by `save_baseline`.
+pub fn load_baseline(path: &Path) -> Result<Vec<Measurement>, RloxError> {
+    let text = fs::read_to_string(path)?;
+    let invalid = || RloxError::from(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} is not a benchmark baseline", path.display()),
+    ));
+    let value: Value = serde_json::from_str(&text).map_err(|_| invalid())?;
+    let benchmarks = value.as_object().ok_or_else(invalid)?;
+    benchmarks.iter().map(|(name, result)| {
+        let seconds = result["seconds"].as_f64().ok_or_else(invalid)?;
+        let allocations = result["allocations"].as_u64().ok_or_else(invalid)?;
+        Ok(Measurement { name: name.clone(), time: Duration::from_secs_f64(seconds), allocations: allocations as usize })
+    }).collect()
+}
+
+pub fn save_baseline(path: &Path, measurements: &[Measurement]) -> Result<(), RloxError> {
+    let benchmarks: Map<String, Value> = measurements.iter().map(|measurement| {
+        let result = json!({ "seconds": measurement.time.as_secs_f64(), "allocations": measurement.allocations });
+        (measurement.name.clone(), result)
+    }).collect();
+    fs::write(path, serde_json::to_string_pretty(&Value::Object(benchmarks)).unwrap() + "\n")?;
+    Ok(())
+}
+
+/// How much `new` changed from `old`, in percent.
+fn change(old: f64, new: f64) -> f64 {
+    if old == 0.0 {
+        0.0
+    } else {
+        (new - old) / old * 100.0
+    }
+}
+
+/// Compares a measurement with its baseline. Returns the text to report and
+/// whether it is a regression.
+pub fn compare(measurement: &Measurement, baseline: &Measurement, threshold: f64) -> (String, bool) {
+    let time = change(baseline.time.as_secs_f64(), measurement.time.as_secs_f64());
+    let allocations = change(baseline.allocations as f64, measurement.allocations as f64);
+    let regressed = time > threshold || allocations > threshold;
+    let text = format!("{:+.1}% time, {:+.1}% allocations{}", time, allocations, if regressed { "  REGRESSION" } else { "" });
+    (text, regressed)
+}
+
+/// The `.lox` scripts in `dir`, by name.
+fn discover(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "lox"));
+    paths.sort();
+    Ok(paths)
+}
+
+/// Runs every benchmark in `dir` and writes a table of the results to `output`.
+/// Returns false if a benchmark failed or regressed against the baseline.
+pub fn run<W: Write>(dir: &Path, options: &BenchOptions, mut output: W) -> Result<bool, RloxError> {
+    let baseline = match &options.baseline {
+        Some(path) => Some(load_baseline(path)?),
+        None => None,
+    };
+    let mut measurements = Vec::new();
+    let mut ok = true;
+    writeln!(output, "{:<20} {:>10} {:>12}", "benchmark", "time", "allocations")?;
+    for path in discover(dir)? {
+        let measurement = match measure(&path, options.runs) {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                ok = false;
+                writeln!(output, "FAIL {}: {}", path.display(), error.message())?;
+                continue;
+            }
+        };
+        write!(output, "{:<20} {:>9.3}s {:>12}", measurement.name, measurement.time.as_secs_f64(), measurement.allocations)?;
+        let old = baseline.iter().flatten().find(|old| old.name == measurement.name);
+        match old {
+            Some(old) => {
+                let (text, regressed) = compare(&measurement, old, options.threshold);
+                ok &= !regressed;
+                writeln!(output, "   {}", text)?;
+            }
+            None if baseline.is_some() => writeln!(output, "   (not in baseline)")?,
+            None => writeln!(output)?,
+        }
+        measurements.push(measurement);
+    }
+    if let Some(path) = &options.save {
+        save_baseline(path, &measurements)?;
+        writeln!(output, "\nSaved baseline to {}", path.display())?;
+    }
+    Ok(ok)
+}
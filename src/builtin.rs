@@ -1,13 +1,17 @@
 ///! builtin functions
 
-use crate::value::{LoxFunction, LoxValue};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::value::{LoxFunction, LoxValue, NativeFn};
 use crate::env::Environment;
 use crate::error::RloxError;
+use crate::interpreter::Interpreter;
 
 /// Macros to initialize built-in functions
 macro_rules! init_builtin {
     ($env:expr, $name:expr, $arity:expr, $impl:expr) => {
-        $env.define_globally($name, LoxValue::Callable(LoxFunction::BuiltInFunction($arity, $impl)));
+        $env.define_globally($name, LoxValue::Callable(LoxFunction::BuiltInFunction($name, $arity, $impl)));
     };
 }
 
@@ -15,6 +19,86 @@ pub fn regist_builtins(env: &mut Environment) {
     init_builtin!(env, "clock", 0, clock_impl);
     init_builtin!(env, "input", 0, input_impl);
     init_builtin!(env, "parseNumber", 1, parse_number_impl);
+    init_builtin!(env, "assert", 2, assert_impl);
+    init_builtin!(env, "assertEqual", 2, assert_equal_impl);
+
+    init_builtin!(env, "sqrt", 1, sqrt_impl);
+    init_builtin!(env, "pow", 2, pow_impl);
+    init_builtin!(env, "abs", 1, abs_impl);
+    init_builtin!(env, "floor", 1, floor_impl);
+    init_builtin!(env, "ceil", 1, ceil_impl);
+    init_builtin!(env, "round", 1, round_impl);
+    init_builtin!(env, "min", 2, min_impl);
+    init_builtin!(env, "max", 2, max_impl);
+    init_builtin!(env, "sin", 1, sin_impl);
+    init_builtin!(env, "cos", 1, cos_impl);
+    init_builtin!(env, "tan", 1, tan_impl);
+    init_builtin!(env, "atan2", 2, atan2_impl);
+    init_builtin!(env, "log", 1, log_impl);
+    init_builtin!(env, "exp", 1, exp_impl);
+    init_builtin!(env, "div", 2, div_impl);
+    for (name, value) in CONSTANTS {
+        env.define_globally(name, LoxValue::Number(value));
+    }
+
+    for &(name, arity, implementation) in STRING_FUNCTIONS {
+        init_builtin!(env, name, arity, implementation);
+    }
+}
+
+/// String functions, with the arity of their global form. Those taking a
+/// string or a list first can also be called as methods of it.
+const STRING_FUNCTIONS: &[(&str, u32, NativeFn)] = &[
+    ("len", 1, len_impl),
+    ("substring", 3, substring_impl),
+    ("indexOf", 2, index_of_impl),
+    ("split", 2, split_impl),
+    ("join", 2, join_impl),
+    ("trim", 1, trim_impl),
+    ("upper", 1, upper_impl),
+    ("lower", 1, lower_impl),
+    ("replace", 3, replace_impl),
+    ("startsWith", 2, starts_with_impl),
+    ("endsWith", 2, ends_with_impl),
+    ("charAt", 2, char_at_impl),
+    ("chr", 1, chr_impl),
+    ("ord", 1, ord_impl),
+];
+
+const STRING_METHODS: &[&str] = &[
+    "len", "substring", "indexOf", "split", "trim", "upper", "lower",
+    "replace", "startsWith", "endsWith", "charAt", "ord",
+];
+
+/// List methods; lists have no global functions of their own besides `len` and `join`.
+const LIST_METHODS: &[(&str, u32, NativeFn)] = &[
+    ("len", 1, len_impl),
+    ("get", 2, get_impl),
+    ("join", 2, join_impl),
+];
+
+/// Looks up a native method of a string or list, bound to that receiver.
+pub fn method(receiver: &LoxValue, name: &str) -> Option<LoxFunction> {
+    let (name, arity, implementation) = match receiver {
+        LoxValue::String(_) if STRING_METHODS.contains(&name) => {
+            STRING_FUNCTIONS.iter().find(|(function, _, _)| *function == name)?
+        }
+        LoxValue::List(_) => LIST_METHODS.iter().find(|(function, _, _)| *function == name)?,
+        _ => return None,
+    };
+    Some(LoxFunction::BoundBuiltIn(Box::new(receiver.clone()), name, arity - 1, *implementation))
+}
+
+/// Global constants defined next to the built-in functions.
+pub const CONSTANTS: [(&str, f64); 2] = [("PI", std::f64::consts::PI), ("E", std::f64::consts::E)];
+
+/// Tells whether a global is one of the built-ins rather than something a program defined.
+pub fn is_builtin(name: &str, value: &LoxValue) -> bool {
+    match value {
+        LoxValue::Callable(LoxFunction::BuiltInFunction(..)) => true,
+        LoxValue::Number(n) => CONSTANTS.iter().any(|(constant, c)| *constant == name && c == n),
+        _ => false,
+    }
 }
 
 fn clock_impl(_args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
@@ -33,7 +117,7 @@ fn input_impl(_args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
     use std::io;
     let mut input = String::new();
     io::stdin().read_line(&mut input).unwrap();
-    Ok(LoxValue::String(input.trim().to_string()))
+    Ok(LoxValue::String(input.trim().to_string().into()))
 }
 
 fn parse_number_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
@@ -49,4 +133,254 @@ fn parse_number_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
         }
         _ => Err(RloxError::RuntimeError("parseNumber(): Need a string argument".to_string())),
     }
-}
\ No newline at end of file
+}
+
+fn assert_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    if Interpreter::is_truthy(&args[0]) {
+        Ok(LoxValue::Null)
+    } else {
+        Err(RloxError::RuntimeError(format!("assert(): {}", args[1].to_string())))
+    }
+}
+
+fn assert_equal_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    if args[0] == args[1] {
+        Ok(LoxValue::Null)
+    } else {
+        Err(RloxError::RuntimeError(format!("assertEqual(): {} is not equal to {}", args[0].to_string(), args[1].to_string())))
+    }
+}
+
+/// Checks that all arguments of a native function are numbers and returns them.
+fn numbers<const N: usize>(name: &str, args: &[LoxValue]) -> Result<[f64; N], RloxError> {
+    let mut numbers = [0.0; N];
+    for (number, arg) in numbers.iter_mut().zip(args) {
+        match arg {
+            LoxValue::Number(n) => *number = *n,
+            _ if N == 1 => return Err(RloxError::RuntimeError(format!("{}(): Need a number argument", name))),
+            _ => return Err(RloxError::RuntimeError(format!("{}(): Need number arguments", name))),
+        }
+    }
+    Ok(numbers)
+}
+
+fn sqrt_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("sqrt", &args)?;
+    Ok(LoxValue::Number(x.sqrt()))
+}
+
+fn pow_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [base, exponent] = numbers("pow", &args)?;
+    Ok(LoxValue::Number(base.powf(exponent)))
+}
+
+fn abs_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("abs", &args)?;
+    Ok(LoxValue::Number(x.abs()))
+}
+
+fn floor_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("floor", &args)?;
+    Ok(LoxValue::Number(x.floor()))
+}
+
+fn ceil_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("ceil", &args)?;
+    Ok(LoxValue::Number(x.ceil()))
+}
+
+/// Rounds half-way cases away from zero.
+fn round_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("round", &args)?;
+    Ok(LoxValue::Number(x.round()))
+}
+
+fn min_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [a, b] = numbers("min", &args)?;
+    Ok(LoxValue::Number(a.min(b)))
+}
+
+fn max_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [a, b] = numbers("max", &args)?;
+    Ok(LoxValue::Number(a.max(b)))
+}
+
+fn sin_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("sin", &args)?;
+    Ok(LoxValue::Number(x.sin()))
+}
+
+fn cos_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("cos", &args)?;
+    Ok(LoxValue::Number(x.cos()))
+}
+
+fn tan_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("tan", &args)?;
+    Ok(LoxValue::Number(x.tan()))
+}
+
+fn atan2_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [y, x] = numbers("atan2", &args)?;
+    Ok(LoxValue::Number(y.atan2(x)))
+}
+
+/// Natural logarithm.
+fn log_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("log", &args)?;
+    Ok(LoxValue::Number(x.ln()))
+}
+
+fn exp_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [x] = numbers("exp", &args)?;
+    Ok(LoxValue::Number(x.exp()))
+}
+
+/// Integer division, truncating towards zero like `%` does, so that
+/// `div(a, b) * b + a % b == a`.
+fn div_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let [a, b] = numbers("div", &args)?;
+    if b == 0.0 {
+        return Err(RloxError::RuntimeError("div(): Division by zero".to_string()));
+    }
+    Ok(LoxValue::Number((a / b).trunc()))
+}
+
+fn string_arg<'a>(name: &str, arg: &'a LoxValue) -> Result<&'a str, RloxError> {
+    match arg {
+        LoxValue::String(s) => Ok(s),
+        _ => Err(RloxError::RuntimeError(format!("{}(): Need a string argument", name))),
+    }
+}
+
+/// Indexes count characters, not bytes, so that non-ASCII text is handled correctly.
+fn index_arg(name: &str, arg: &LoxValue) -> Result<usize, RloxError> {
+    match arg {
+        LoxValue::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        _ => Err(RloxError::RuntimeError(format!("{}(): Need a non-negative integer index", name))),
+    }
+}
+
+fn list_arg(name: &str, arg: &LoxValue) -> Result<Rc<RefCell<Vec<LoxValue>>>, RloxError> {
+    match arg {
+        LoxValue::List(items) => Ok(Rc::clone(items)),
+        _ => Err(RloxError::RuntimeError(format!("{}(): Need a list argument", name))),
+    }
+}
+
+fn len_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    match &args[0] {
+        LoxValue::String(s) => Ok(LoxValue::Number(s.chars().count() as f64)),
+        LoxValue::List(items) => Ok(LoxValue::Number(items.borrow().len() as f64)),
+        _ => Err(RloxError::RuntimeError("len(): Need a string or list argument".to_string())),
+    }
+}
+
+/// Characters from `start` up to, but not including, `end`.
+fn substring_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let s = string_arg("substring", &args[0])?;
+    let start = index_arg("substring", &args[1])?;
+    let end = index_arg("substring", &args[2])?;
+    if start > end || end > s.chars().count() {
+        return Err(RloxError::RuntimeError("substring(): Index out of range".to_string()));
+    }
+    Ok(LoxValue::String(s.chars().skip(start).take(end - start).collect::<String>().into()))
+}
+
+/// Character index of the first occurrence, or -1.
+fn index_of_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let s = string_arg("indexOf", &args[0])?;
+    let needle = string_arg("indexOf", &args[1])?;
+    match s.find(needle) {
+        Some(at) => Ok(LoxValue::Number(s[..at].chars().count() as f64)),
+        None => Ok(LoxValue::Number(-1.0)),
+    }
+}
+
+/// Splits on every occurrence of the separator, or into characters if it is empty.
+fn split_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let s = string_arg("split", &args[0])?;
+    let separator = string_arg("split", &args[1])?;
+    let parts: Vec<LoxValue> = if separator.is_empty() {
+        s.chars().map(|c| LoxValue::String(c.to_string().into())).collect()
+    } else {
+        s.split(separator).map(|part| LoxValue::String(part.to_string().into())).collect()
+    };
+    Ok(LoxValue::List(Rc::new(RefCell::new(parts))))
+}
+
+fn join_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let items = list_arg("join", &args[0])?;
+    let separator = string_arg("join", &args[1])?;
+    let items: Vec<String> = items.borrow().iter().map(LoxValue::to_string).collect();
+    Ok(LoxValue::String(items.join(separator).into()))
+}
+
+fn trim_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    Ok(LoxValue::String(string_arg("trim", &args[0])?.trim().to_string().into()))
+}
+
+fn upper_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    Ok(LoxValue::String(string_arg("upper", &args[0])?.to_uppercase().into()))
+}
+
+fn lower_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    Ok(LoxValue::String(string_arg("lower", &args[0])?.to_lowercase().into()))
+}
+
+/// Replaces every occurrence.
+fn replace_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let s = string_arg("replace", &args[0])?;
+    let from = string_arg("replace", &args[1])?;
+    let to = string_arg("replace", &args[2])?;
+    Ok(LoxValue::String(s.replace(from, to).into()))
+}
+
+fn starts_with_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let s = string_arg("startsWith", &args[0])?;
+    Ok(LoxValue::Boolean(s.starts_with(string_arg("startsWith", &args[1])?)))
+}
+
+fn ends_with_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let s = string_arg("endsWith", &args[0])?;
+    Ok(LoxValue::Boolean(s.ends_with(string_arg("endsWith", &args[1])?)))
+}
+
+fn char_at_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let s = string_arg("charAt", &args[0])?;
+    let index = index_arg("charAt", &args[1])?;
+    match s.chars().nth(index) {
+        Some(c) => Ok(LoxValue::String(c.to_string().into())),
+        None => Err(RloxError::RuntimeError("charAt(): Index out of range".to_string())),
+    }
+}
+
+/// The character with the given Unicode code point.
+fn chr_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let code = match &args[0] {
+        LoxValue::Number(n) if n.fract() == 0.0 && *n >= 0.0 && *n <= u32::MAX as f64 => char::from_u32(*n as u32),
+        LoxValue::Number(_) => None,
+        _ => return Err(RloxError::RuntimeError("chr(): Need a number argument".to_string())),
+    };
+    match code {
+        Some(c) => Ok(LoxValue::String(c.to_string().into())),
+        None => Err(RloxError::RuntimeError("chr(): Invalid code point".to_string())),
+    }
+}
+
+/// The Unicode code point of a one-character string.
+fn ord_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let s = string_arg("ord", &args[0])?;
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(LoxValue::Number(c as u32 as f64)),
+        _ => Err(RloxError::RuntimeError("ord(): Need a single character".to_string())),
+    }
+}
+
+fn get_impl(args: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+    let items = list_arg("get", &args[0])?;
+    let index = index_arg("get", &args[1])?;
+    let item = items.borrow().get(index).cloned();
+    item.ok_or_else(|| RloxError::RuntimeError("get(): Index out of range".to_string()))
+}
@@ -0,0 +1,510 @@
+//! Checker:
+//! A gradual type checker that runs after the resolver.
+//!
+//! Variables, parameters and function results may be annotated with a type:
+//! `Number`, `String`, `Bool`, `Nil`, `Function`, `Any` or the name of a class,
+//! whose instances it then stands for. Unannotated code stays dynamically typed,
+//! with its variables of type `Any`, but the checker still infers the types of
+//! literals, operators, calls of annotated functions and constructors.
+//!
+//! A mismatch is reported when a value goes into an annotated variable, parameter
+//! or result, and when an operator is applied to a value whose type comes from an
+//! annotation. `nil` may stand in for instances and functions. The interpreter
+//! ignores annotations, so checked programs behave as they did before.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::ast::expr::{Expr, LiteralValue};
+use crate::ast::stmt::{Signature, Stmt};
+use crate::ast::token::{Token, TokenType};
+use crate::error::RloxError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Any,
+    Nil,
+    Bool,
+    Number,
+    String,
+    /// A function, with its signature if it is known.
+    Function(Option<Rc<FunctionType>>),
+    /// A class itself, which is called to make instances.
+    Class(String),
+    Instance(String),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Any => write!(f, "Any"),
+            Type::Nil => write!(f, "Nil"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Number => write!(f, "Number"),
+            Type::String => write!(f, "String"),
+            Type::Function(_) => write!(f, "Function"),
+            Type::Class(name) => write!(f, "class {}", name),
+            Type::Instance(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FunctionType {
+    pub params: Vec<Type>,
+    pub returns: Type,
+}
+
+/// The type of an expression, and whether it comes from an annotation rather
+/// than only from inference.
+#[derive(Debug, Clone)]
+struct Typed {
+    ty: Type,
+    declared: bool,
+}
+
+impl Typed {
+    fn inferred(ty: Type) -> Typed {
+        Typed { ty, declared: false }
+    }
+
+    fn any() -> Typed {
+        Typed::inferred(Type::Any)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClassInfo {
+    superclass: Option<String>,
+    methods: HashMap<String, Rc<FunctionType>>,
+}
+
+pub struct Checker {
+    pub had_error: bool,
+    /// Errors met while checking, kept for tools that report them on their own.
+    pub errors: Vec<RloxError>,
+    /// If set, errors are only collected into `errors` and not printed.
+    pub silent: bool,
+    scopes: Vec<HashMap<String, Typed>>,
+    classes: HashMap<String, ClassInfo>,
+    /// The annotated result type of each enclosing function, innermost last.
+    returns: Vec<Option<Type>>,
+    /// The enclosing classes, innermost last.
+    current_class: Vec<String>,
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Checker {
+            had_error: false,
+            errors: Vec::new(),
+            silent: false,
+            scopes: Vec::new(),
+            classes: HashMap::new(),
+            returns: Vec::new(),
+            current_class: Vec::new(),
+        }
+    }
+
+    pub fn check_program(&mut self, program: &Stmt) {
+        if let Stmt::Program(declarations) = program {
+            self.collect_classes(declarations);
+            self.check_block(declarations);
+        }
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        let error = RloxError::SemanticError(line, message);
+        if !self.silent {
+            println!("{}", error);
+        }
+        self.errors.push(error);
+        self.had_error = true;
+    }
+
+    /// Finds the classes declared anywhere in the program, so that annotations
+    /// may name classes declared after them.
+    fn collect_classes(&mut self, program: &[Stmt]) {
+        let mut classes = vec![];
+        find_classes(program, &mut classes);
+        for (name, _, _) in &classes {
+            self.classes.insert(name.lexeme.clone(), ClassInfo::default());
+        }
+        for (name, superclass, methods) in classes {
+            let mut info = ClassInfo { superclass, methods: HashMap::new() };
+            for method in methods {
+                if let Stmt::FunctionDecl(method_name, _, _, _, signature) = method {
+                    info.methods.insert(method_name.lexeme.clone(), self.function_type(signature, false));
+                }
+            }
+            self.classes.insert(name.lexeme.clone(), info);
+        }
+    }
+
+    /// The type an annotation names. Unknown names are reported if `report` is
+    /// set, and checked as `Any`.
+    fn annotation(&mut self, annotation: &Option<Token>, report: bool) -> Type {
+        let Some(annotation) = annotation else {
+            return Type::Any;
+        };
+        match annotation.lexeme.as_str() {
+            "Any" => Type::Any,
+            "Nil" => Type::Nil,
+            "Bool" => Type::Bool,
+            "Number" => Type::Number,
+            "String" => Type::String,
+            "Function" => Type::Function(None),
+            name if self.classes.contains_key(name) => Type::Instance(name.to_string()),
+            name => {
+                if report {
+                    self.error(annotation.line, format!("Unknown type '{}'.", name));
+                }
+                Type::Any
+            }
+        }
+    }
+
+    fn function_type(&mut self, signature: &Signature, report: bool) -> Rc<FunctionType> {
+        let params = signature.params.iter().map(|param| self.annotation(param, report)).collect();
+        let returns = self.annotation(&signature.returns, report);
+        Rc::new(FunctionType { params, returns })
+    }
+
+    /// Whether a value of type `actual` may be used where `expected` is required.
+    fn accepts(&self, expected: &Type, actual: &Type) -> bool {
+        match (expected, actual) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Function(_), Type::Function(_) | Type::Class(_)) => true,
+            (Type::Function(_) | Type::Instance(_), Type::Nil) => true,
+            (Type::Instance(expected), Type::Instance(actual)) => self.is_subclass(actual, expected),
+            (expected, actual) => expected == actual,
+        }
+    }
+
+    fn is_subclass(&self, class: &str, ancestor: &str) -> bool {
+        let mut class = Some(class);
+        // the depth limit guards against cycles between classes of the same name
+        for _ in 0..=self.classes.len() {
+            match class {
+                Some(name) if name == ancestor => return true,
+                Some(name) => class = self.classes.get(name).and_then(|info| info.superclass.as_deref()),
+                None => return false,
+            }
+        }
+        false
+    }
+
+    fn find_method(&self, class: &str, name: &str) -> Option<Rc<FunctionType>> {
+        let mut class = Some(class);
+        for _ in 0..=self.classes.len() {
+            let info = self.classes.get(class?)?;
+            if let Some(method) = info.methods.get(name) {
+                return Some(Rc::clone(method));
+            }
+            class = info.superclass.as_deref();
+        }
+        None
+    }
+
+    fn define(&mut self, name: &Token, typed: Typed) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), typed);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Typed {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .unwrap_or_else(Typed::any)
+    }
+
+    /// Checks statements in a scope of their own. Functions and classes are
+    /// defined first, so that code may call them before their declaration.
+    fn check_block(&mut self, stmts: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            match stmt {
+                Stmt::FunctionDecl(name, _, _, _, signature) => {
+                    let function = self.function_type(signature, false);
+                    let declared = is_annotated(&function);
+                    self.define(name, Typed { ty: Type::Function(Some(function)), declared });
+                }
+                Stmt::ClassDecl(name, ..) => {
+                    self.define(name, Typed::inferred(Type::Class(name.lexeme.clone())));
+                }
+                _ => {}
+            }
+        }
+        for stmt in stmts {
+            self.check_stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Program(stmts) | Stmt::Block(stmts) => self.check_block(stmts),
+            Stmt::Test(_, _, body) => self.check_block(body),
+            Stmt::Expression(expression, _) | Stmt::Print(expression, _) => {
+                self.infer(expression);
+            }
+            Stmt::Var(name, initializer, annotation) => {
+                let expected = self.annotation(annotation, true);
+                if let Some(initializer) = initializer {
+                    let actual = self.infer(initializer);
+                    if !self.accepts(&expected, &actual.ty) {
+                        self.error(name.line, format!("Cannot assign a value of type '{}' to '{}' of type '{}'.",
+                            actual.ty, name.lexeme, expected));
+                    }
+                }
+                self.define(name, Typed { ty: expected, declared: annotation.is_some() });
+            }
+            Stmt::If(condition, then_branch, else_branch, _) => {
+                self.infer(condition);
+                self.check_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.check_stmt(else_branch);
+                }
+            }
+            Stmt::While(condition, body, _) => {
+                self.infer(condition);
+                self.check_stmt(body);
+            }
+            Stmt::FunctionDecl(name, params, body, _, signature) => {
+                let function = self.check_function(params, body, signature);
+                let declared = is_annotated(&function);
+                self.define(name, Typed { ty: Type::Function(Some(function)), declared });
+            }
+            Stmt::Return(keyword, value) => {
+                let actual = match value {
+                    Some(value) => self.infer(value),
+                    None => Typed::inferred(Type::Nil),
+                };
+                if let Some(Some(expected)) = self.returns.last().cloned() {
+                    if !self.accepts(&expected, &actual.ty) {
+                        self.error(keyword.line, format!("Cannot return a value of type '{}' from a function that returns '{}'.",
+                            actual.ty, expected));
+                    }
+                }
+            }
+            Stmt::ClassDecl(name, super_class, methods, _) => {
+                if let Some(super_class) = super_class {
+                    self.infer(super_class);
+                }
+                self.define(name, Typed::inferred(Type::Class(name.lexeme.clone())));
+                self.current_class.push(name.lexeme.clone());
+                for method in methods {
+                    if let Stmt::FunctionDecl(_, params, body, _, signature) = method {
+                        self.check_function(params, body, signature);
+                    }
+                }
+                self.current_class.pop();
+            }
+        }
+    }
+
+    fn check_function(&mut self, params: &[Token], body: &[Stmt], signature: &Signature) -> Rc<FunctionType> {
+        let function = self.function_type(signature, true);
+        self.scopes.push(HashMap::new());
+        for ((param, ty), annotation) in params.iter().zip(&function.params).zip(&signature.params) {
+            self.define(param, Typed { ty: ty.clone(), declared: annotation.is_some() });
+        }
+        self.returns.push(signature.returns.as_ref().map(|_| function.returns.clone()));
+        self.check_block(body);
+        self.returns.pop();
+        self.scopes.pop();
+        function
+    }
+
+    fn infer(&mut self, expr: &Expr) -> Typed {
+        match expr {
+            Expr::Literal(value) => Typed::inferred(match value {
+                LiteralValue::Number(_) => Type::Number,
+                LiteralValue::String(_) => Type::String,
+                LiteralValue::Boolean(_) => Type::Bool,
+                LiteralValue::Nil => Type::Nil,
+            }),
+            Expr::Grouping(expression) => self.infer(expression),
+            Expr::Interpolation(parts) => {
+                for part in parts {
+                    self.infer(part);
+                }
+                Typed::inferred(Type::String)
+            }
+            Expr::Unary(operator, right) => {
+                let right = self.infer(right);
+                if operator.t_type == TokenType::Bang {
+                    return Typed::inferred(Type::Bool);
+                }
+                if right.declared && !self.accepts(&Type::Number, &right.ty) {
+                    self.error(operator.line, format!("Operand of '-' must be a number, found '{}'.", right.ty));
+                }
+                Typed { ty: Type::Number, declared: right.declared }
+            }
+            Expr::Binary(left, operator, right) => {
+                let left = self.infer(left);
+                let right = self.infer(right);
+                self.binary(operator, left, right)
+            }
+            Expr::Logical(left, _, right) => {
+                let left = self.infer(left);
+                let right = self.infer(right);
+                if left.ty == right.ty {
+                    Typed { ty: left.ty, declared: left.declared && right.declared }
+                } else {
+                    Typed::any()
+                }
+            }
+            Expr::Variable(name, _) => self.lookup(&name.lexeme),
+            Expr::Assign(name, value, _) => {
+                let value = self.infer(value);
+                let target = self.lookup(&name.lexeme);
+                if target.declared && !self.accepts(&target.ty, &value.ty) {
+                    self.error(name.line, format!("Cannot assign a value of type '{}' to '{}' of type '{}'.",
+                        value.ty, name.lexeme, target.ty));
+                }
+                value
+            }
+            Expr::Call(callee, arguments, line, _) => {
+                let function = self.infer(callee);
+                let arguments: Vec<Typed> = arguments.iter().map(|argument| self.infer(argument)).collect();
+                self.call(callee, function, &arguments, *line)
+            }
+            Expr::Get(object, name) => {
+                // a field of the same name hides a method at runtime, so only
+                // a method with annotations is taken to be what the read finds
+                match self.infer(object).ty {
+                    Type::Instance(class) => match self.find_method(&class, &name.lexeme) {
+                        Some(method) if is_annotated(&method) => Typed { ty: Type::Function(Some(method)), declared: true },
+                        _ => Typed::any(),
+                    },
+                    _ => Typed::any(),
+                }
+            }
+            Expr::Set(object, _, value) => {
+                self.infer(object);
+                self.infer(value)
+            }
+            Expr::This(..) => match self.current_class.last() {
+                Some(class) => Typed::inferred(Type::Instance(class.clone())),
+                None => Typed::any(),
+            },
+            Expr::Super(_, method, _) => {
+                let superclass = self.current_class.last()
+                    .and_then(|class| self.classes.get(class))
+                    .and_then(|info| info.superclass.clone());
+                match superclass.and_then(|superclass| self.find_method(&superclass, &method.lexeme)) {
+                    Some(method) if is_annotated(&method) => Typed { ty: Type::Function(Some(method)), declared: true },
+                    _ => Typed::any(),
+                }
+            }
+        }
+    }
+
+    fn binary(&mut self, operator: &Token, left: Typed, right: Typed) -> Typed {
+        let declared = left.declared || right.declared;
+        let known = |ty: &Type| *ty != Type::Any;
+        match operator.t_type {
+            TokenType::EqualEqual | TokenType::BangEqual => Typed::inferred(Type::Bool),
+            TokenType::Plus => {
+                let addable = |ty: &Type| matches!(ty, Type::Number | Type::String | Type::Any);
+                let mismatch = !addable(&left.ty) || !addable(&right.ty)
+                    || (known(&left.ty) && known(&right.ty) && left.ty != right.ty);
+                if declared && mismatch {
+                    self.error(operator.line, format!("Operands of '+' must be two numbers or two strings, found '{}' and '{}'.",
+                        left.ty, right.ty));
+                    return Typed::any();
+                }
+                let ty = if known(&left.ty) { left.ty } else { right.ty };
+                Typed { ty, declared }
+            }
+            _ => {
+                if declared && (!self.accepts(&Type::Number, &left.ty) || !self.accepts(&Type::Number, &right.ty)) {
+                    self.error(operator.line, format!("Operands of '{}' must be numbers, found '{}' and '{}'.",
+                        operator.lexeme, left.ty, right.ty));
+                }
+                let ty = match operator.t_type {
+                    TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => Type::Bool,
+                    _ => Type::Number,
+                };
+                Typed { ty, declared }
+            }
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, function: Typed, arguments: &[Typed], line: usize) -> Typed {
+        match function.ty {
+            Type::Function(Some(function)) => {
+                self.check_arguments(callee, &function, arguments, line);
+                Typed { declared: function.returns != Type::Any, ty: function.returns.clone() }
+            }
+            Type::Class(class) => {
+                if let Some(init) = self.find_method(&class, "init") {
+                    self.check_arguments(callee, &init, arguments, line);
+                }
+                Typed::inferred(Type::Instance(class))
+            }
+            Type::Nil | Type::Bool | Type::Number | Type::String | Type::Instance(_) if function.declared => {
+                self.error(line, format!("Can only call functions and classes, found '{}'.", function.ty));
+                Typed::any()
+            }
+            _ => Typed::any(),
+        }
+    }
+
+    fn check_arguments(&mut self, callee: &Expr, function: &FunctionType, arguments: &[Typed], line: usize) {
+        for (i, (expected, actual)) in function.params.iter().zip(arguments).enumerate() {
+            if !self.accepts(expected, &actual.ty) {
+                self.error(line, format!("Argument {} of '{}' must be of type '{}', found '{}'.",
+                    i + 1, callee_name(callee), expected, actual.ty));
+            }
+        }
+    }
+}
+
+/// Whether any parameter or the result of a function has a type annotation.
+fn is_annotated(function: &FunctionType) -> bool {
+    function.returns != Type::Any || function.params.iter().any(|param| *param != Type::Any)
+}
+
+/// The classes declared in `stmts`, at any depth: their names, superclasses and methods.
+fn find_classes<'s>(stmts: &'s [Stmt], classes: &mut Vec<(&'s Token, Option<String>, &'s [Stmt])>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::ClassDecl(name, super_class, methods, _) => {
+                let superclass = match super_class {
+                    Some(Expr::Variable(super_name, _)) => Some(super_name.lexeme.clone()),
+                    _ => None,
+                };
+                classes.push((name, superclass, methods));
+                find_classes(methods, classes);
+            }
+            Stmt::Program(stmts) | Stmt::Block(stmts) => find_classes(stmts, classes),
+            Stmt::FunctionDecl(_, _, body, ..) | Stmt::Test(_, _, body) => find_classes(body, classes),
+            Stmt::If(_, then_branch, else_branch, _) => {
+                find_classes(std::slice::from_ref(&**then_branch), classes);
+                if let Some(else_branch) = else_branch {
+                    find_classes(std::slice::from_ref(&**else_branch), classes);
+                }
+            }
+            Stmt::While(_, body, _) => find_classes(std::slice::from_ref(&**body), classes),
+            _ => {}
+        }
+    }
+}
+
+/// A name for the function a call expression calls, for error messages.
+fn callee_name(callee: &Expr) -> String {
+    match callee {
+        Expr::Variable(name, _) | Expr::Get(_, name) | Expr::Super(_, name, _) => name.lexeme.clone(),
+        _ => "function".to_string(),
+    }
+}
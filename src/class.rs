@@ -3,6 +3,7 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
+use crate::intern::Symbol;
 use crate::value::LoxValue;
 use crate::value::LoxFunction;
 use crate::error::RloxError;
@@ -11,13 +12,14 @@ use crate::error::RloxError;
 pub struct LoxClass {
     pub name: String,
     pub super_class: Option<Rc<RefCell<LoxClass>>>,
-    pub methods: HashMap<String, LoxFunction>,
+    /// Methods by name, including the inherited ones that are not overridden.
+    pub methods: HashMap<Symbol, Rc<LoxFunction>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct LoxInstance {
     pub class: Rc<RefCell<LoxClass>>,
-    fields: HashMap<String, LoxValue>,
+    fields: HashMap<Symbol, LoxValue>,
 }
 
 impl LoxClass {
@@ -29,16 +31,18 @@ impl LoxClass {
         }
     }
 
-    pub fn find_method(&self, name: &str) -> Option<LoxFunction> {
-        if let Some(method) = self.methods.get(name) {
-            return Some(method.clone());
-        }
-
-        if let Some(super_class) = &self.super_class {
-            return super_class.borrow().find_method(name);
+    /// Creates a subclass, which starts out with all the methods of its superclass.
+    pub fn with_super_class(name: String, super_class: Rc<RefCell<LoxClass>>) -> Self {
+        let methods = super_class.borrow().methods.clone();
+        LoxClass {
+            name,
+            super_class: Some(super_class),
+            methods,
         }
+    }
 
-        None
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        self.methods.get(name).cloned()
     }
 }
 
@@ -56,8 +60,12 @@ impl LoxInstance {
         }
     }
 
+    pub fn fields(&self) -> &HashMap<Symbol, LoxValue> {
+        &self.fields
+    }
+
     pub fn set(&mut self, name: &str, value: LoxValue) {
-        self.fields.insert(name.to_string(), value);
+        self.fields.insert(Symbol::intern(name), value);
     }
 
     pub fn get(&self, name: &str, instance: &Rc<RefCell<LoxInstance>>) -> Result<LoxValue, RloxError> {
@@ -0,0 +1,151 @@
+//! Coverage:
+//! Records which statements ran and which arms of each branch were taken,
+//! and writes the result as an LCOV report.
+//!
+//! Every statement and branch of the program is registered before it runs,
+//! so that code which was never reached shows up in the report as well.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::ast::expr::Expr;
+use crate::ast::stmt::Stmt;
+use crate::error::RloxError;
+use crate::hook::Hook;
+use crate::interpreter::Interpreter;
+
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// Executions per line that holds at least one statement.
+    pub lines: BTreeMap<usize, u64>,
+    /// Times each arm was taken, per branch at (line, column).
+    /// Arm 0 runs the body or the right operand, arm 1 skips it.
+    pub branches: BTreeMap<(usize, usize), [u64; 2]>,
+}
+
+impl Coverage {
+    /// Registers all statements and branches of `program` as not yet executed.
+    pub fn new(program: &Stmt) -> Self {
+        let mut coverage = Coverage::default();
+        coverage.register_stmt(program);
+        coverage
+    }
+
+    fn register_stmt(&mut self, stmt: &Stmt) {
+        if let Some(line) = stmt.line() {
+            self.lines.entry(line).or_insert(0);
+        }
+        match stmt {
+            Stmt::Program(stmts) | Stmt::Block(stmts) | Stmt::ClassDecl(_, None, stmts, _) => {
+                stmts.iter().for_each(|s| self.register_stmt(s));
+            }
+            Stmt::ClassDecl(_, Some(super_class), stmts, _) => {
+                self.register_expr(super_class);
+                stmts.iter().for_each(|s| self.register_stmt(s));
+            }
+            Stmt::FunctionDecl(_, _, body, _, _) | Stmt::Test(_, _, body) => {
+                body.iter().for_each(|s| self.register_stmt(s));
+            }
+            Stmt::Var(_, initializer, _) | Stmt::Return(_, initializer) => {
+                if let Some(initializer) = initializer {
+                    self.register_expr(initializer);
+                }
+            }
+            Stmt::Expression(expr, _) | Stmt::Print(expr, _) => self.register_expr(expr),
+            Stmt::If(condition, then_branch, else_branch, keyword) => {
+                self.branches.entry((keyword.line, keyword.column)).or_default();
+                self.register_expr(condition);
+                self.register_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.register_stmt(else_branch);
+                }
+            }
+            Stmt::While(condition, body, keyword) => {
+                self.branches.entry((keyword.line, keyword.column)).or_default();
+                self.register_expr(condition);
+                self.register_stmt(body);
+            }
+        }
+    }
+
+    fn register_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Logical(left, operator, right) => {
+                self.branches.entry((operator.line, operator.column)).or_default();
+                self.register_expr(left);
+                self.register_expr(right);
+            }
+            Expr::Binary(left, _, right) | Expr::Set(left, _, right) => {
+                self.register_expr(left);
+                self.register_expr(right);
+            }
+            Expr::Grouping(inner) | Expr::Unary(_, inner) | Expr::Assign(_, inner, _) | Expr::Get(inner, _) => {
+                self.register_expr(inner);
+            }
+            Expr::Call(callee, arguments, ..) => {
+                self.register_expr(callee);
+                arguments.iter().for_each(|a| self.register_expr(a));
+            }
+            Expr::Interpolation(parts) => parts.iter().for_each(|p| self.register_expr(p)),
+            Expr::Literal(_) | Expr::Variable(..) | Expr::This(..) | Expr::Super(..) => {}
+        }
+    }
+
+    /// Writes the report for one source file in LCOV's tracefile format.
+    pub fn write_lcov<W: Write>(&self, source_file: &str, mut output: W) -> io::Result<()> {
+        writeln!(output, "TN:")?;
+        writeln!(output, "SF:{}", source_file)?;
+
+        // LCOV numbers the branches of one line as blocks
+        let mut block = 0;
+        let mut previous_line = 0;
+        let mut branches_hit = 0;
+        for (&(line, _), arms) in &self.branches {
+            block = if line == previous_line { block + 1 } else { 0 };
+            previous_line = line;
+            let reached = arms.iter().any(|&count| count > 0);
+            for (arm, &count) in arms.iter().enumerate() {
+                if reached {
+                    writeln!(output, "BRDA:{},{},{},{}", line, block, arm, count)?;
+                } else {
+                    writeln!(output, "BRDA:{},{},{},-", line, block, arm)?;
+                }
+                if count > 0 {
+                    branches_hit += 1;
+                }
+            }
+        }
+        writeln!(output, "BRF:{}", self.branches.len() * 2)?;
+        writeln!(output, "BRH:{}", branches_hit)?;
+
+        for (line, count) in &self.lines {
+            writeln!(output, "DA:{},{}", line, count)?;
+        }
+        writeln!(output, "LF:{}", self.lines.len())?;
+        writeln!(output, "LH:{}", self.lines.values().filter(|&&count| count > 0).count())?;
+        writeln!(output, "end_of_record")
+    }
+
+    /// A one-line summary such as `lines: 9/10 (90.0%), branches: 3/4 (75.0%)`.
+    pub fn summary(&self) -> String {
+        let lines_hit = self.lines.values().filter(|&&count| count > 0).count();
+        let branches_hit = self.branches.values().flatten().filter(|&&count| count > 0).count();
+        let percent = |hit: usize, total: usize| {
+            if total == 0 { 100.0 } else { hit as f64 * 100.0 / total as f64 }
+        };
+        format!("lines: {}/{} ({:.1}%), branches: {}/{} ({:.1}%)",
+            lines_hit, self.lines.len(), percent(lines_hit, self.lines.len()),
+            branches_hit, self.branches.len() * 2, percent(branches_hit, self.branches.len() * 2))
+    }
+}
+
+impl Hook for Coverage {
+    fn before_stmt(&mut self, _interpreter: &mut Interpreter, _stmt: &Stmt, line: usize) -> Result<(), RloxError> {
+        *self.lines.entry(line).or_insert(0) += 1;
+        Ok(())
+    }
+
+    fn on_branch(&mut self, _interpreter: &Interpreter, line: usize, column: usize, arm: usize) {
+        self.branches.entry((line, column)).or_default()[arm] += 1;
+    }
+}
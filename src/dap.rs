@@ -0,0 +1,420 @@
+//! Debug adapter:
+//! Speaks the Debug Adapter Protocol over stdio, so that editors can debug
+//! Lox scripts. It drives the same statement hook and `Stepper` as the
+//! console debugger.
+//!
+//! The adapter is single threaded: while the program runs, requests are only
+//! read when it pauses. Scopes are built from the `EnvItem` chain of the
+//! paused environment, and instances can be expanded into their fields.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use serde_json::{json, Value};
+
+use crate::ast::stmt::Stmt;
+use crate::builtin::is_builtin;
+use crate::checker::Checker;
+use crate::class::LoxInstance;
+use crate::debugger::{self, StepMode, Stepper};
+use crate::env::EnvItem;
+use crate::error::RloxError;
+use crate::hook::Hook;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::rpc::{read_message, write_message};
+use crate::scanner::Scanner;
+use crate::value::LoxValue;
+
+const THREAD_ID: u64 = 1;
+
+struct Connection<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    seq: u64,
+    disconnected: bool,
+}
+
+type Shared<R, W> = Rc<RefCell<Connection<R, W>>>;
+
+impl<R: BufRead, W: Write> Connection<R, W> {
+    fn read(&mut self) -> io::Result<Option<Value>> {
+        let message = read_message(&mut self.input)?;
+        if message.is_none() {
+            self.disconnected = true;
+        }
+        Ok(message)
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.output, &message)
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Value, message: &str) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+}
+
+/// Sends everything the program prints as `output` events, one per line.
+struct OutputEvents<R: BufRead, W: Write> {
+    connection: Shared<R, W>,
+    pending: String,
+}
+
+impl<R: BufRead, W: Write> Write for OutputEvents<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.connection.borrow().disconnected {
+            return Ok(buf.len());
+        }
+        self.pending.push_str(&String::from_utf8_lossy(buf));
+        while let Some(end) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=end).collect();
+            self.connection.borrow_mut().event("output", json!({ "category": "stdout", "output": line }))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let rest = std::mem::take(&mut self.pending);
+            self.connection.borrow_mut().event("output", json!({ "category": "stdout", "output": rest }))?;
+        }
+        Ok(())
+    }
+}
+
+/// Something the client can expand through a `variablesReference`.
+enum Container {
+    Scope(Rc<RefCell<EnvItem>>),
+    Globals(Rc<RefCell<EnvItem>>),
+    Instance(Rc<RefCell<LoxInstance>>),
+}
+
+struct DapHook<R: BufRead, W: Write> {
+    connection: Shared<R, W>,
+    stepper: Stepper,
+    path: String,
+    containers: Vec<Container>,   // reference n is containers[n - 1], valid while paused
+    entry: bool,
+}
+
+/// Runs the adapter until the client disconnects or closes the input.
+pub fn run_adapter<R: BufRead + 'static, W: Write + 'static>(input: R, output: W) -> io::Result<()> {
+    let connection = Rc::new(RefCell::new(Connection { input, output, seq: 0, disconnected: false }));
+    let mut program: Option<(String, String)> = None;     // (path, source)
+    let mut stop_on_entry = false;
+    let mut breakpoints: HashSet<usize> = HashSet::new();
+
+    // configuration: everything up to `configurationDone`
+    loop {
+        let Some(request) = connection.borrow_mut().read()? else {
+            return Ok(());
+        };
+        let mut conn = connection.borrow_mut();
+        match request["command"].as_str().unwrap_or("") {
+            "initialize" => {
+                conn.respond(&request, json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsEvaluateForHovers": true,
+                }))?;
+                conn.event("initialized", json!({}))?;
+            }
+            "launch" => {
+                let path = request["arguments"]["program"].as_str().unwrap_or("").to_string();
+                stop_on_entry = request["arguments"]["stopOnEntry"].as_bool().unwrap_or(false);
+                match std::fs::read_to_string(&path) {
+                    Ok(source) => {
+                        program = Some((path, source));
+                        conn.respond(&request, json!({}))?;
+                    }
+                    Err(e) => conn.fail(&request, &format!("Cannot read '{}': {}", path, e))?,
+                }
+            }
+            "setBreakpoints" => {
+                let body = set_breakpoints(&mut breakpoints, &request);
+                conn.respond(&request, body)?;
+            }
+            "threads" => conn.respond(&request, threads())?,
+            "configurationDone" => {
+                conn.respond(&request, json!({}))?;
+                break;
+            }
+            "disconnect" => {
+                conn.respond(&request, json!({}))?;
+                return Ok(());
+            }
+            _ => conn.respond(&request, json!({}))?,
+        }
+    }
+
+    if let Some((path, source)) = program {
+        let mut stepper = Stepper::new(if stop_on_entry { StepMode::StepIn } else { StepMode::Continue });
+        stepper.breakpoints = breakpoints;
+        launch(&connection, path, source, stepper)?;
+    }
+    if connection.borrow().disconnected {
+        return Ok(());
+    }
+    connection.borrow_mut().event("terminated", json!({}))?;
+    connection.borrow_mut().event("exited", json!({ "exitCode": 0 }))?;
+
+    while !connection.borrow().disconnected {
+        let Some(request) = connection.borrow_mut().read()? else {
+            break;
+        };
+        let mut conn = connection.borrow_mut();
+        match request["command"].as_str().unwrap_or("") {
+            "disconnect" => {
+                conn.respond(&request, json!({}))?;
+                break;
+            }
+            "threads" => conn.respond(&request, threads())?,
+            _ => conn.fail(&request, "The program has terminated")?,
+        }
+    }
+    Ok(())
+}
+
+fn launch<R: BufRead + 'static, W: Write + 'static>(connection: &Shared<R, W>, path: String, source: String,
+    stepper: Stepper) -> io::Result<()> {
+    let mut interpreter = Interpreter::new();
+    let mut errors = vec![];
+
+    let mut scanner = Scanner::new(source);
+    scanner.silent = true;
+    let tokens = scanner.scan_tokens();
+    errors.append(&mut scanner.errors);
+    let mut parser = Parser::new(tokens);
+    parser.silent = true;
+    let program = parser.parse();
+    errors.append(&mut parser.errors);
+    if let (Some(program), true) = (&program, errors.is_empty()) {
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.silent = true;
+        resolver.resolve_program(program);
+        errors.append(&mut resolver.errors);
+        if errors.is_empty() {
+            let mut checker = Checker::new();
+            checker.silent = true;
+            checker.check_program(program);
+            errors.append(&mut checker.errors);
+        }
+    }
+
+    if !errors.is_empty() {
+        let mut conn = connection.borrow_mut();
+        for error in errors {
+            conn.event("output", json!({ "category": "stderr", "output": format!("{}\n", error) }))?;
+        }
+        return Ok(());
+    }
+
+    interpreter.output = Box::new(OutputEvents { connection: Rc::clone(connection), pending: String::new() });
+    interpreter.hook = Some(Box::new(DapHook {
+        connection: Rc::clone(connection),
+        entry: stepper.mode == StepMode::StepIn,
+        stepper,
+        path,
+        containers: vec![],
+    }));
+    if let Some(program) = program {
+        interpreter.interpret(program);
+    }
+    interpreter.output.flush()
+}
+
+fn threads() -> Value {
+    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })
+}
+
+fn set_breakpoints(breakpoints: &mut HashSet<usize>, request: &Value) -> Value {
+    breakpoints.clear();
+    let lines: Vec<usize> = request["arguments"]["breakpoints"].as_array()
+        .map(|list| list.iter().filter_map(|b| b["line"].as_u64()).map(|l| l as usize).collect())
+        .unwrap_or_default();
+    breakpoints.extend(lines.iter().copied());
+    let verified: Vec<Value> = lines.iter().map(|line| json!({ "verified": true, "line": line })).collect();
+    json!({ "breakpoints": verified })
+}
+
+fn type_name(value: &LoxValue) -> &'static str {
+    match value {
+        LoxValue::Number(_) => "number",
+        LoxValue::String(_) => "string",
+        LoxValue::Boolean(_) => "boolean",
+        LoxValue::Null => "nil",
+        LoxValue::Callable(_) => "function",
+        LoxValue::Class(_) => "class",
+        LoxValue::Instance(_) => "instance",
+        LoxValue::List(_) => "list",
+    }
+}
+
+impl<R: BufRead, W: Write> DapHook<R, W> {
+    fn reference(&mut self, container: Container) -> usize {
+        self.containers.push(container);
+        self.containers.len()
+    }
+
+    fn variable(&mut self, name: &str, value: &LoxValue) -> Value {
+        let reference = match value {
+            LoxValue::Instance(instance) => self.reference(Container::Instance(Rc::clone(instance))),
+            _ => 0,
+        };
+        json!({
+            "name": name,
+            "value": value.to_string(),
+            "type": type_name(value),
+            "variablesReference": reference,
+        })
+    }
+
+    fn scopes(&mut self, interpreter: &Interpreter, frame: u64) -> Value {
+        let mut scopes = vec![];
+        // only the innermost frame still has its environment at hand
+        if frame == 0 {
+            let mut current = Some(Rc::clone(&interpreter.env.values));
+            while let Some(item) = current {
+                if Rc::ptr_eq(&item, &interpreter.env.global) {
+                    break;
+                }
+                let name = if scopes.is_empty() { "Locals" } else { "Enclosing" };
+                current = item.borrow().parent.clone();
+                let reference = self.reference(Container::Scope(item));
+                scopes.push(json!({ "name": name, "variablesReference": reference, "expensive": false }));
+            }
+        }
+        let reference = self.reference(Container::Globals(Rc::clone(&interpreter.env.global)));
+        scopes.push(json!({ "name": "Globals", "variablesReference": reference, "expensive": false }));
+        json!({ "scopes": scopes })
+    }
+
+    fn variables(&mut self, reference: usize) -> Value {
+        let mut entries: Vec<(String, LoxValue)> = match self.containers.get(reference.wrapping_sub(1)) {
+            Some(Container::Scope(item)) => item.borrow().variables(),
+            Some(Container::Globals(item)) => item.borrow().table.iter()
+                .filter(|(name, value)| !is_builtin(name, value))
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+            Some(Container::Instance(instance)) => instance.borrow().fields().iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+            None => vec![],
+        };
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let variables: Vec<Value> = entries.iter().map(|(name, value)| self.variable(name, value)).collect();
+        json!({ "variables": variables })
+    }
+
+    fn stack_trace(&self, interpreter: &Interpreter) -> Value {
+        let name = std::path::Path::new(&self.path).file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let frames: Vec<Value> = debugger::backtrace(interpreter).iter().enumerate()
+            .map(|(id, frame)| json!({
+                "id": id,
+                "name": frame.name.as_str(),
+                "line": frame.line,
+                "column": 1,
+                "source": { "name": name, "path": self.path },
+            }))
+            .collect();
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    /// Answers requests until one of them resumes the program.
+    fn paused(&mut self, interpreter: &mut Interpreter) -> Result<(), RloxError> {
+        let depth = interpreter.frames.len();
+        loop {
+            let Some(request) = self.connection.borrow_mut().read()? else {
+                self.stepper.mode = StepMode::Continue;
+                return Ok(());
+            };
+            let arguments = &request["arguments"];
+            let (body, resume) = match request["command"].as_str().unwrap_or("") {
+                "threads" => (threads(), None),
+                "stackTrace" => (self.stack_trace(interpreter), None),
+                "scopes" => (self.scopes(interpreter, arguments["frameId"].as_u64().unwrap_or(0)), None),
+                "variables" => (self.variables(arguments["variablesReference"].as_u64().unwrap_or(0) as usize), None),
+                "setBreakpoints" => (set_breakpoints(&mut self.stepper.breakpoints, &request), None),
+                "evaluate" => {
+                    let expression = arguments["expression"].as_str().unwrap_or("");
+                    match debugger::evaluate(interpreter, expression) {
+                        Ok(value) => {
+                            let variable = self.variable(expression, &value);
+                            (json!({ "result": variable["value"], "type": variable["type"],
+                                "variablesReference": variable["variablesReference"] }), None)
+                        }
+                        Err(error) => {
+                            self.connection.borrow_mut().fail(&request, &error.to_string())?;
+                            continue;
+                        }
+                    }
+                }
+                "continue" => (json!({ "allThreadsContinued": true }), Some(StepMode::Continue)),
+                "next" => (json!({}), Some(StepMode::StepOver(depth))),
+                "stepIn" => (json!({}), Some(StepMode::StepIn)),
+                "stepOut" => (json!({}), Some(StepMode::StepOut(depth))),
+                "disconnect" | "terminate" => {
+                    let mut conn = self.connection.borrow_mut();
+                    conn.respond(&request, json!({}))?;
+                    conn.disconnected = true;
+                    return Err(RloxError::RuntimeError("Program stopped by debugger.".to_string()));
+                }
+                _ => (json!({}), None),
+            };
+            self.connection.borrow_mut().respond(&request, body)?;
+            if let Some(mode) = resume {
+                self.stepper.mode = mode;
+                self.containers.clear();
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Hook for DapHook<R, W> {
+    fn before_stmt(&mut self, interpreter: &mut Interpreter, _stmt: &Stmt, line: usize) -> Result<(), RloxError> {
+        if self.connection.borrow().disconnected || !self.stepper.should_pause(line, interpreter.frames.len()) {
+            return Ok(());
+        }
+        let reason = if std::mem::take(&mut self.entry) {
+            "entry"
+        } else if self.stepper.breakpoints.contains(&line) {
+            "breakpoint"
+        } else {
+            "step"
+        };
+        interpreter.output.flush()?;
+        self.connection.borrow_mut().event("stopped", json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        }))?;
+        self.paused(interpreter)
+    }
+}
@@ -0,0 +1,258 @@
+//! Debugger:
+//! A line debugger built on the interpreter's statement hook. It supports
+//! breakpoints, stepping, backtraces, inspection of locals and evaluating
+//! expressions in the paused frame.
+//!
+//! The inspection helpers are public so that other front ends can share them.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use crate::ast::stmt::Stmt;
+use crate::builtin::is_builtin;
+use crate::env::EnvItem;
+use crate::error::RloxError;
+use crate::hook::Hook;
+use crate::interpreter::{CallFrame, Interpreter};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::value::LoxValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepMode {
+    Continue,
+    StepIn,
+    StepOver(usize),    // pause at the next statement at most this deep
+    StepOut(usize),     // pause at the next statement shallower than this
+}
+
+/// Decides where a running program should pause.
+#[derive(Debug)]
+pub struct Stepper {
+    pub breakpoints: HashSet<usize>,
+    pub mode: StepMode,
+    last: Option<(usize, usize)>,   // (line, depth) of the previous statement
+}
+
+impl Stepper {
+    pub fn new(mode: StepMode) -> Self {
+        Stepper {
+            breakpoints: HashSet::new(),
+            mode,
+            last: None,
+        }
+    }
+
+    /// Called before every statement. `depth` is the number of active frames.
+    pub fn should_pause(&mut self, line: usize, depth: usize) -> bool {
+        let stepped = match self.mode {
+            StepMode::Continue => false,
+            StepMode::StepIn => true,
+            StepMode::StepOver(d) => depth <= d,
+            StepMode::StepOut(d) => depth < d,
+        };
+        // a breakpoint fires once when execution arrives at its line,
+        // not again for each statement on that line
+        let arrived = self.last != Some((line, depth));
+        let hit = self.breakpoints.contains(&line) && arrived;
+        self.last = Some((line, depth));
+        stepped || hit
+    }
+}
+
+/// Active frames, innermost first.
+pub fn backtrace(interpreter: &Interpreter) -> Vec<CallFrame> {
+    interpreter.frames.iter().rev().cloned().collect()
+}
+
+/// Variables of every scope from the innermost one outwards, excluding globals.
+pub fn local_scopes(interpreter: &Interpreter) -> Vec<Vec<(String, LoxValue)>> {
+    let mut scopes = vec![];
+    let mut current = Some(Rc::clone(&interpreter.env.values));
+    while let Some(item) = current {
+        if Rc::ptr_eq(&item, &interpreter.env.global) {
+            break;
+        }
+        scopes.push(scope_variables(&item));
+        current = item.borrow().parent.clone();
+    }
+    scopes
+}
+
+/// Globals defined by the program; built-in functions and constants are left out.
+pub fn globals(interpreter: &Interpreter) -> Vec<(String, LoxValue)> {
+    scope_variables(&interpreter.env.global).into_iter()
+        .filter(|(name, value)| !is_builtin(name, value))
+        .collect()
+}
+
+fn scope_variables(item: &Rc<RefCell<EnvItem>>) -> Vec<(String, LoxValue)> {
+    let mut variables = item.borrow().variables();
+    variables.sort_by(|a, b| a.0.cmp(&b.0));
+    variables
+}
+
+/// Evaluates an expression in the current environment of a paused program.
+/// Names are looked up dynamically through the scope chain.
+pub fn evaluate(interpreter: &mut Interpreter, source: &str) -> Result<LoxValue, RloxError> {
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.silent = true;
+    let tokens = scanner.scan_tokens();
+    if let Some(error) = scanner.errors.pop() {
+        return Err(error);
+    }
+    // ids past the last bound one, so every name is looked up dynamically
+    let mut parser = Parser::new(tokens);
+    parser.silent = true;
+    parser.next_id = interpreter.locals.len();
+    let expression = parser.parse_expr();
+    if let Some(error) = parser.errors.pop() {
+        return Err(error);
+    }
+    match expression {
+        Some(expression) => {
+            // statements run by the expression must not pause again
+            let hook = interpreter.hook.take();
+            let result = expression.accept(interpreter);
+            interpreter.hook = hook;
+            result
+        }
+        None => Err(RloxError::RuntimeError("Expected expression.".to_string())),
+    }
+}
+
+const HELP: &str = "\
+Commands:
+  break N, b N      set a breakpoint at line N
+  delete N          remove the breakpoint at line N
+  step, s           step into the next statement
+  next, n           step over calls
+  finish, f         run until the current function returns
+  continue, c       run until the next breakpoint
+  backtrace, bt     show active calls
+  locals            show local variables
+  globals           show global variables
+  print EXPR, p     evaluate an expression in the current frame
+  list              show the source around the current line
+  quit, q           stop the program";
+
+/// Interactive debugger reading commands from `input`.
+pub struct ConsoleDebugger<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    stepper: Stepper,
+    source: Vec<String>,
+}
+
+impl<R: BufRead, W: Write> ConsoleDebugger<R, W> {
+    /// Creates a debugger that pauses before the first statement.
+    pub fn new(source: &str, input: R, output: W) -> Self {
+        ConsoleDebugger {
+            input,
+            output,
+            stepper: Stepper::new(StepMode::StepIn),
+            source: source.lines().map(str::to_string).collect(),
+        }
+    }
+
+    fn show_line(&mut self, line: usize) -> std::io::Result<()> {
+        let text = self.source.get(line.wrapping_sub(1)).map(String::as_str).unwrap_or("");
+        writeln!(self.output, "{:>4} | {}", line, text)
+    }
+
+    /// Reads commands until one of them resumes execution.
+    fn prompt(&mut self, interpreter: &mut Interpreter, line: usize) -> Result<(), RloxError> {
+        let depth = interpreter.frames.len();
+        loop {
+            write!(self.output, "(rlox-debug) ")?;
+            self.output.flush()?;
+            let mut command = String::new();
+            if self.input.read_line(&mut command)? == 0 {
+                // no more commands: let the program finish
+                self.stepper.mode = StepMode::Continue;
+                return Ok(());
+            }
+            let command = command.trim();
+            let (name, argument) = command.split_once(' ')
+                .map(|(name, argument)| (name, argument.trim()))
+                .unwrap_or((command, ""));
+            match name {
+                "" => continue,
+                "step" | "s" => {
+                    self.stepper.mode = StepMode::StepIn;
+                    return Ok(());
+                }
+                "next" | "n" => {
+                    self.stepper.mode = StepMode::StepOver(depth);
+                    return Ok(());
+                }
+                "finish" | "f" => {
+                    self.stepper.mode = StepMode::StepOut(depth);
+                    return Ok(());
+                }
+                "continue" | "c" => {
+                    self.stepper.mode = StepMode::Continue;
+                    return Ok(());
+                }
+                "quit" | "q" => {
+                    return Err(RloxError::RuntimeError("Program stopped by debugger.".to_string()));
+                }
+                "break" | "b" | "delete" => match argument.parse::<usize>() {
+                    Ok(at) if name == "delete" => {
+                        self.stepper.breakpoints.remove(&at);
+                        writeln!(self.output, "Deleted breakpoint at line {}", at)?;
+                    }
+                    Ok(at) => {
+                        self.stepper.breakpoints.insert(at);
+                        writeln!(self.output, "Breakpoint set at line {}", at)?;
+                    }
+                    Err(_) => writeln!(self.output, "Expected a line number")?,
+                },
+                "backtrace" | "bt" => {
+                    for (i, frame) in backtrace(interpreter).iter().enumerate() {
+                        writeln!(self.output, "#{} {} at line {}", i, frame.name, frame.line)?;
+                    }
+                }
+                "locals" => {
+                    let scopes = local_scopes(interpreter);
+                    if scopes.iter().all(Vec::is_empty) {
+                        writeln!(self.output, "No locals")?;
+                    }
+                    for (name, value) in scopes.into_iter().flatten() {
+                        writeln!(self.output, "{} = {}", name, value.to_string())?;
+                    }
+                }
+                "globals" => {
+                    for (name, value) in globals(interpreter) {
+                        writeln!(self.output, "{} = {}", name, value.to_string())?;
+                    }
+                }
+                "print" | "p" => match evaluate(interpreter, argument) {
+                    Ok(value) => writeln!(self.output, "{}", value.to_string())?,
+                    Err(error) => writeln!(self.output, "{}", error)?,
+                },
+                "list" => {
+                    let from = line.saturating_sub(2).max(1);
+                    for at in from..=(line + 2).min(self.source.len()) {
+                        self.show_line(at)?;
+                    }
+                }
+                "help" | "h" => writeln!(self.output, "{}", HELP)?,
+                _ => writeln!(self.output, "Unknown command '{}', try 'help'", name)?,
+            }
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Hook for ConsoleDebugger<R, W> {
+    fn before_stmt(&mut self, interpreter: &mut Interpreter, _stmt: &Stmt, line: usize) -> Result<(), RloxError> {
+        if !self.stepper.should_pause(line, interpreter.frames.len()) {
+            return Ok(());
+        }
+        interpreter.output.flush()?;
+        self.show_line(line)?;
+        self.prompt(interpreter, line)
+    }
+}
@@ -0,0 +1,439 @@
+//! Doc:
+//! Generates documentation for Lox source files from their top-level
+//! functions and classes and the `///` comments written before them.
+//!
+//! The site has one page per source file and an index of everything
+//! documented. Classes link to their superclass and list their subclasses,
+//! wherever among the documented files these are declared.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::ast::expr::Expr;
+use crate::ast::stmt::Stmt;
+use crate::error::RloxError;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::testing::discover;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "md" | "markdown" => Some(Format::Markdown),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Html => "html",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDoc {
+    pub name: String,
+    /// The parameters, with their type annotations if they have one, e.g. `a: Number`.
+    pub params: Vec<String>,
+    pub returns: Option<String>,
+    pub doc: Option<String>,
+    pub line: usize,
+}
+
+impl FunctionDoc {
+    fn from_stmt(stmt: &Stmt) -> Option<FunctionDoc> {
+        match stmt {
+            Stmt::FunctionDecl(name, params, _, doc, signature) => Some(FunctionDoc {
+                name: name.lexeme.clone(),
+                params: params.iter().zip(&signature.params).map(|(param, annotation)| match annotation {
+                    Some(annotation) => format!("{}: {}", param.lexeme, annotation.lexeme),
+                    None => param.lexeme.clone(),
+                }).collect(),
+                returns: signature.returns.as_ref().map(|annotation| annotation.lexeme.clone()),
+                doc: doc.clone(),
+                line: name.line,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The name with the parameter list and result type, e.g. `add(a, b)` or
+    /// `add(a: Number, b: Number): Number`.
+    pub fn signature(&self) -> String {
+        match &self.returns {
+            Some(returns) => format!("{}({}): {}", self.name, self.params.join(", "), returns),
+            None => format!("{}({})", self.name, self.params.join(", ")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDoc {
+    pub name: String,
+    pub superclass: Option<String>,
+    pub doc: Option<String>,
+    pub line: usize,
+    pub methods: Vec<FunctionDoc>,
+}
+
+impl ClassDoc {
+    /// The class as declared, e.g. `class Circle < Shape`.
+    pub fn header(&self) -> String {
+        match &self.superclass {
+            Some(superclass) => format!("class {} < {}", self.name, superclass),
+            None => format!("class {}", self.name),
+        }
+    }
+}
+
+/// The documented declarations of one source file, in source order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleDoc {
+    pub functions: Vec<FunctionDoc>,
+    pub classes: Vec<ClassDoc>,
+}
+
+impl ModuleDoc {
+    pub fn from_program(program: &Stmt) -> ModuleDoc {
+        let mut module = ModuleDoc::default();
+        let Stmt::Program(stmts) = program else {
+            return module;
+        };
+        for stmt in stmts {
+            match stmt {
+                Stmt::FunctionDecl(..) => module.functions.extend(FunctionDoc::from_stmt(stmt)),
+                Stmt::ClassDecl(name, super_class, methods, doc) => module.classes.push(ClassDoc {
+                    name: name.lexeme.clone(),
+                    superclass: match super_class {
+                        Some(Expr::Variable(super_name, _)) => Some(super_name.lexeme.clone()),
+                        _ => None,
+                    },
+                    doc: doc.clone(),
+                    line: name.line,
+                    methods: methods.iter().filter_map(FunctionDoc::from_stmt).collect(),
+                }),
+                _ => {}
+            }
+        }
+        module
+    }
+
+    /// Scans and parses `source` without printing errors.
+    pub fn from_source(source: &str) -> Result<ModuleDoc, Vec<RloxError>> {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.silent = true;
+        let tokens = scanner.scan_tokens();
+        if scanner.had_error {
+            return Err(scanner.errors);
+        }
+        let mut parser = Parser::new(tokens);
+        parser.silent = true;
+        match parser.parse() {
+            Some(program) if !parser.had_error => Ok(ModuleDoc::from_program(&program)),
+            _ => Err(parser.errors),
+        }
+    }
+}
+
+/// A source file and the page documenting it.
+#[derive(Debug)]
+pub struct Page {
+    /// The path of the source file, relative to the documented directory.
+    pub source: String,
+    /// The file name of the page, without extension.
+    pub name: String,
+    pub module: ModuleDoc,
+}
+
+impl Page {
+    fn file_name(&self, format: Format) -> String {
+        format!("{}.{}", self.name, format.extension())
+    }
+}
+
+/// Where each class is documented, for links between classes and their superclasses.
+struct Site<'a> {
+    pages: &'a [Page],
+    format: Format,
+    classes: BTreeMap<&'a str, (&'a Page, &'a ClassDoc)>,
+}
+
+impl<'a> Site<'a> {
+    fn new(pages: &'a [Page], format: Format) -> Site<'a> {
+        let mut classes = BTreeMap::new();
+        for page in pages {
+            for class in &page.module.classes {
+                classes.entry(class.name.as_str()).or_insert((page, class));
+            }
+        }
+        Site { pages, format, classes }
+    }
+
+    fn class_link(&self, name: &str) -> Option<String> {
+        self.classes.get(name).map(|(page, _)| format!("{}#class-{}", page.file_name(self.format), name))
+    }
+
+    fn subclasses(&self, name: &str) -> Vec<&'a str> {
+        self.classes.values()
+            .filter(|(_, class)| class.superclass.as_deref() == Some(name))
+            .map(|(_, class)| class.name.as_str())
+            .collect()
+    }
+}
+
+/// Renders the index and one page per source file, as (file name, content) pairs.
+pub fn render(pages: &[Page], format: Format) -> Vec<(String, String)> {
+    let site = Site::new(pages, format);
+    let mut files = vec![(format!("index.{}", format.extension()), render_index(&site))];
+    for page in pages {
+        files.push((page.file_name(format), render_page(&site, page)));
+    }
+    files
+}
+
+/// Documents every `.lox` file under `root` into the directory `out`.
+/// Files that do not parse are reported to `output` and left out.
+/// Returns whether all files could be documented.
+pub fn generate<W: Write>(root: &Path, out: &Path, format: Format, mut output: W) -> io::Result<bool> {
+    let mut pages = vec![];
+    let mut all_parsed = true;
+    for path in discover(&[root.to_path_buf()])? {
+        let relative = path.strip_prefix(root).ok()
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .or_else(|| path.file_name().map(Path::new))
+            .unwrap_or(&path)
+            .to_path_buf();
+        let source = relative.to_string_lossy().replace('\\', "/");
+        match ModuleDoc::from_source(&fs::read_to_string(&path)?) {
+            Ok(module) => {
+                let name = source.trim_end_matches(".lox").replace('/', ".");
+                pages.push(Page { source, name, module });
+            }
+            Err(errors) => {
+                all_parsed = false;
+                for error in errors {
+                    writeln!(output, "{}: {}", path.display(), error)?;
+                }
+            }
+        }
+    }
+
+    fs::create_dir_all(out)?;
+    let files = render(&pages, format);
+    for (name, content) in &files {
+        fs::write(out.join(name), content)?;
+    }
+    writeln!(output, "Wrote {} pages to {}", files.len(), out.display())?;
+    Ok(all_parsed)
+}
+
+fn render_index(site: &Site) -> String {
+    let mut classes: Vec<(&Page, &ClassDoc)> = site.classes.values().copied().collect();
+    classes.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+    let mut functions: Vec<(&Page, &FunctionDoc)> = site.pages.iter()
+        .flat_map(|page| page.module.functions.iter().map(move |function| (page, function)))
+        .collect();
+    functions.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+
+    let mut doc = Document::new(site.format, "Documentation");
+    doc.heading(1, None, &[Text::Plain("Documentation")]);
+    doc.heading(2, None, &[Text::Plain("Files")]);
+    doc.list(site.pages.iter()
+        .map(|page| vec![Text::Link(&page.source, page.file_name(site.format))])
+        .collect());
+    if !classes.is_empty() {
+        doc.heading(2, None, &[Text::Plain("Classes")]);
+        doc.list(classes.iter()
+            .map(|(page, class)| {
+                let href = format!("{}#class-{}", page.file_name(site.format), class.name);
+                with_summary(Text::CodeLink(&class.name, href), &class.doc)
+            })
+            .collect());
+    }
+    if !functions.is_empty() {
+        doc.heading(2, None, &[Text::Plain("Functions")]);
+        doc.list(functions.iter()
+            .map(|(page, function)| {
+                let href = format!("{}#fun-{}", page.file_name(site.format), function.name);
+                with_summary(Text::CodeLink(&function.name, href), &function.doc)
+            })
+            .collect());
+    }
+    doc.finish()
+}
+
+fn render_page(site: &Site, page: &Page) -> String {
+    let mut doc = Document::new(site.format, &page.source);
+    doc.heading(1, None, &[Text::Plain(&page.source)]);
+    doc.paragraph(&[Text::Link("Index", format!("index.{}", site.format.extension()))]);
+
+    if !page.module.functions.is_empty() {
+        doc.heading(2, None, &[Text::Plain("Functions")]);
+        for function in &page.module.functions {
+            let signature = format!("fun {}", function.signature());
+            doc.heading(3, Some(&format!("fun-{}", function.name)), &[Text::Code(&signature)]);
+            doc.text(&function.doc);
+        }
+    }
+
+    if !page.module.classes.is_empty() {
+        doc.heading(2, None, &[Text::Plain("Classes")]);
+    }
+    for class in &page.module.classes {
+        let header = format!("class {}", class.name);
+        let mut title = vec![Text::Code(&header)];
+        if let Some(superclass) = &class.superclass {
+            title.push(Text::Plain(" < "));
+            title.push(match site.class_link(superclass) {
+                Some(href) => Text::CodeLink(superclass, href),
+                None => Text::Code(superclass),
+            });
+        }
+        doc.heading(3, Some(&format!("class-{}", class.name)), &title);
+        doc.text(&class.doc);
+
+        let subclasses = site.subclasses(&class.name);
+        if !subclasses.is_empty() {
+            let mut line = vec![Text::Plain("Subclasses: ")];
+            for (i, subclass) in subclasses.iter().enumerate() {
+                if i > 0 {
+                    line.push(Text::Plain(", "));
+                }
+                line.push(Text::CodeLink(subclass, site.class_link(subclass).unwrap_or_default()));
+            }
+            doc.paragraph(&line);
+        }
+
+        for method in &class.methods {
+            let signature = method.signature();
+            doc.heading(4, Some(&format!("{}-{}", class.name, method.name)), &[Text::Code(&signature)]);
+            doc.text(&method.doc);
+        }
+    }
+    doc.finish()
+}
+
+/// An index entry, followed by the first line of its doc comment if it has one.
+fn with_summary<'a>(entry: Text<'a>, doc: &'a Option<String>) -> Vec<Text<'a>> {
+    match doc.as_deref().and_then(|doc| doc.lines().next()) {
+        Some(summary) => vec![entry, Text::Plain(" — "), Text::Plain(summary)],
+        None => vec![entry],
+    }
+}
+
+/// A piece of inline text.
+enum Text<'a> {
+    Plain(&'a str),
+    Code(&'a str),
+    Link(&'a str, String),
+    CodeLink(&'a str, String),
+}
+
+/// Builds a page in either format from headings, paragraphs and lists.
+struct Document {
+    format: Format,
+    content: String,
+}
+
+impl Document {
+    fn new(format: Format, title: &str) -> Document {
+        let content = match format {
+            Format::Markdown => String::new(),
+            Format::Html => format!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n",
+                escape_html(title)),
+        };
+        Document { format, content }
+    }
+
+    fn finish(mut self) -> String {
+        if self.format == Format::Html {
+            self.content.push_str("</body>\n</html>\n");
+        }
+        self.content
+    }
+
+    fn inline(&self, texts: &[Text]) -> String {
+        texts.iter()
+            .map(|text| match (self.format, text) {
+                (Format::Markdown, Text::Plain(text)) => text.to_string(),
+                (Format::Markdown, Text::Code(code)) => format!("`{}`", code),
+                (Format::Markdown, Text::Link(text, href)) => format!("[{}]({})", text, href),
+                (Format::Markdown, Text::CodeLink(code, href)) => format!("[`{}`]({})", code, href),
+                (Format::Html, Text::Plain(text)) => escape_html(text),
+                (Format::Html, Text::Code(code)) => format!("<code>{}</code>", escape_html(code)),
+                (Format::Html, Text::Link(text, href)) => format!("<a href=\"{}\">{}</a>", escape_html(href), escape_html(text)),
+                (Format::Html, Text::CodeLink(code, href)) => {
+                    format!("<a href=\"{}\"><code>{}</code></a>", escape_html(href), escape_html(code))
+                }
+            })
+            .collect()
+    }
+
+    fn heading(&mut self, level: usize, id: Option<&str>, texts: &[Text]) {
+        let inline = self.inline(texts);
+        match (self.format, id) {
+            (Format::Markdown, Some(id)) => {
+                self.content.push_str(&format!("<a id=\"{}\"></a>\n\n{} {}\n\n", id, "#".repeat(level), inline));
+            }
+            (Format::Markdown, None) => self.content.push_str(&format!("{} {}\n\n", "#".repeat(level), inline)),
+            (Format::Html, Some(id)) => {
+                self.content.push_str(&format!("<h{0} id=\"{1}\">{2}</h{0}>\n", level, escape_html(id), inline));
+            }
+            (Format::Html, None) => self.content.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline)),
+        }
+    }
+
+    fn paragraph(&mut self, texts: &[Text]) {
+        let inline = self.inline(texts);
+        match self.format {
+            Format::Markdown => self.content.push_str(&format!("{}\n\n", inline)),
+            Format::Html => self.content.push_str(&format!("<p>{}</p>\n", inline)),
+        }
+    }
+
+    /// A doc comment, whose blank lines separate paragraphs.
+    fn text(&mut self, doc: &Option<String>) {
+        let Some(doc) = doc else {
+            return;
+        };
+        for paragraph in doc.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+            match self.format {
+                Format::Markdown => self.content.push_str(&format!("{}\n\n", paragraph)),
+                Format::Html => self.content.push_str(&format!("<p>{}</p>\n", escape_html(paragraph))),
+            }
+        }
+    }
+
+    fn list(&mut self, items: Vec<Vec<Text>>) {
+        if self.format == Format::Html {
+            self.content.push_str("<ul>\n");
+        }
+        for item in &items {
+            let inline = self.inline(item);
+            match self.format {
+                Format::Markdown => self.content.push_str(&format!("- {}\n", inline)),
+                Format::Html => self.content.push_str(&format!("<li>{}</li>\n", inline)),
+            }
+        }
+        match self.format {
+            Format::Markdown => self.content.push('\n'),
+            Format::Html => self.content.push_str("</ul>\n"),
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
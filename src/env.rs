@@ -4,14 +4,29 @@
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
+use crate::intern::Symbol;
 use crate::value::LoxValue;
 use crate::error::RloxError;
 use crate::ast::token::Token;
 use crate::ast::token::TokenType;
 
+/// Where the resolver found the declaration of a variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// A local, `depth` scopes out from the current one, at `slot` in that scope.
+    Local { depth: usize, slot: usize },
+    /// A global declared before the reference.
+    Global,
+}
+
+/// One scope. The outermost holds the globals by name; the others hold their
+/// locals in slots, numbered in the order they are declared.
 #[derive(Debug)]
 pub struct EnvItem {
-    pub table: HashMap<String, LoxValue>,
+    pub table: HashMap<Symbol, LoxValue>,
+    pub slots: Vec<LoxValue>,
+    /// The name of the local in each slot, for debuggers and dynamic lookups.
+    pub names: Vec<Symbol>,
     pub parent: Option<Rc<RefCell<EnvItem>>>,
 }
 
@@ -19,9 +34,38 @@ impl EnvItem {
     pub fn from_parent(parent: Rc<RefCell<EnvItem>>) -> Self {
         EnvItem {
             table: HashMap::new(),
+            slots: Vec::new(),
+            names: Vec::new(),
             parent: Some(parent),
         }
     }
+
+    /// Declares a variable: a global in the outermost scope, or else the next local slot.
+    pub fn define(&mut self, name: Symbol, value: LoxValue) {
+        if self.parent.is_none() {
+            self.table.insert(name, value);
+        } else {
+            self.names.push(name);
+            self.slots.push(value);
+        }
+    }
+
+    /// The variable called `name` in this scope. Later locals win, as in the
+    /// REPL a name may be declared again.
+    fn lookup(&mut self, name: &str) -> Option<&mut LoxValue> {
+        match self.names.iter().rposition(|local| local.as_str() == name) {
+            Some(slot) => self.slots.get_mut(slot),
+            None => self.table.get_mut(name),
+        }
+    }
+
+    /// The variables of this scope with their names.
+    pub fn variables(&self) -> Vec<(String, LoxValue)> {
+        self.table.iter()
+            .chain(self.names.iter().zip(self.slots.iter()))
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
 }
 
 impl ToString for EnvItem {
@@ -29,7 +73,7 @@ impl ToString for EnvItem {
         let mut s = String::new();
         s.push_str("EnvItem:\n");
         s.push_str("\tkeys: ");
-        for key in self.table.keys() {
+        for key in self.table.keys().chain(self.names.iter()) {
             s.push_str(&format!("{} ", key));
         }
         s.push_str("\n");
@@ -57,6 +101,8 @@ impl Environment {
         let global = Rc::new(RefCell::new(
             EnvItem {
                 table: HashMap::new(),
+                slots: Vec::new(),
+                names: Vec::new(),
                 parent: None,
             }
         ));
@@ -81,14 +127,7 @@ impl Environment {
 
     /// Enter a new scope, which will push a new table onto the stack. 
     pub fn enter_scope(&mut self) {
-        // let curr_stack = std::mem::take(&mut self.values);
-        // self.values = StackItem::Table(HashMap::new(), Box::new(curr_stack));
-        self.values = Rc::new(RefCell::new(
-            EnvItem {
-                table: HashMap::new(),
-                parent: Some(Rc::clone(&self.values)),
-            }
-        ));
+        self.values = Rc::new(RefCell::new(EnvItem::from_parent(Rc::clone(&self.values))));
     }
 
     /// Exit the current scope, which will pop the top table from the stack. <br>
@@ -104,83 +143,72 @@ impl Environment {
         }
     }
 
+    /// Assigns to a variable the resolver did not find, looking it up by name
+    /// from the current scope outwards.
     pub fn assign(&mut self, name: &Token, value: LoxValue) -> Result<(), RloxError> {
         if name.t_type != TokenType::Identifier {
             return Err(RloxError::RuntimeError(format!("Invalid token type '{}'.", name.lexeme)));
         }
-        let name = &name.lexeme;
-        let mut current = Rc::clone(&self.values);
-        loop {
-            if let Some(v) = current.borrow_mut().table.get_mut(name) {
-                *v = value;
+        let mut current = Some(Rc::clone(&self.values));
+        while let Some(item) = current {
+            if let Some(variable) = item.borrow_mut().lookup(&name.lexeme) {
+                *variable = value;
                 return Ok(());
             }
-            {
-                let mut _next: Option<Rc<RefCell<EnvItem>>> = None;
-                if let Some(parent) = &current.borrow_mut().parent {
-                    _next = Some(Rc::clone(parent));
-                } else {
-                    break;
-                }
-                current = _next.unwrap();
-            }
+            current = item.borrow().parent.clone();
         }
-        Err(RloxError::RuntimeError(format!("Undefined variable '{}'.", name)))
+        Err(RloxError::RuntimeError(format!("Undefined variable '{}'.", name.lexeme)))
     }
 
-    pub fn assign_by_depth(&mut self, name: &Token, value: LoxValue, depth: usize) -> Result<(), RloxError> {
-        if name.t_type != TokenType::Identifier {
-            return Err(RloxError::RuntimeError(format!("Invalid token type '{}'.", name.lexeme)));
-        }
-        let name = &name.lexeme;
-        let current = self.ancestor(depth);
-        if let Some(v) = current.borrow_mut().table.get_mut(name) {
-            *v = value;
-            return Ok(());
+    pub fn assign_resolved(&mut self, name: &Token, value: LoxValue, binding: Binding) -> Result<(), RloxError> {
+        let item = match binding {
+            Binding::Local { depth, .. } => self.ancestor(depth),
+            Binding::Global => Rc::clone(&self.global),
+        };
+        let mut item = item.borrow_mut();
+        let variable = match binding {
+            Binding::Local { slot, .. } => item.slots.get_mut(slot),
+            Binding::Global => item.table.get_mut(name.lexeme.as_str()),
+        };
+        match variable {
+            Some(variable) => {
+                *variable = value;
+                Ok(())
+            }
+            None => Err(RloxError::RuntimeError(format!("Undefined variable '{}'.", name.lexeme))),
         }
-        Err(RloxError::RuntimeError(format!("Undefined variable '{}'.", name)))
     }
 
     pub fn define_globally(&mut self, name: &str, value: LoxValue) {
-        self.global.borrow_mut().table.insert(name.to_string(), value);
+        self.global.borrow_mut().table.insert(Symbol::intern(name), value);
     }
 
-    pub fn define(&mut self, name: &str, value: LoxValue) {
-        self.values.borrow_mut().table.insert(name.to_string(), value);
-    }
-
-    fn get_helper(values: &Rc<RefCell<EnvItem>>, name: &str) -> Option<LoxValue> {
-        let values = values.borrow();
-        if let Some(value) = values.table.get(name) {
-            Some(value.clone())
-        } else if let Some(parent) = &values.parent {
-            Self::get_helper(parent, name)
-        } else {
-            None
-        }
+    pub fn define(&mut self, name: Symbol, value: LoxValue) {
+        self.values.borrow_mut().define(name, value);
     }
 
+    /// Reads a variable the resolver did not find, looking it up by name from
+    /// the current scope outwards.
     pub fn get(&self, name: &Token) -> Result<LoxValue, RloxError> {
         if name.t_type != TokenType::Identifier && name.t_type != TokenType::This && name.t_type != TokenType::Super {
             return Err(RloxError::RuntimeError(format!("Invalid token type '{}'.", name.lexeme)));
         }
-        match Self::get_helper(&self.values, &name.lexeme) {
-            Some(value) => Ok(value),
-            None => Err(RloxError::RuntimeError(format!("Undefined variable '{}'.", name.lexeme))),
+        let mut current = Some(Rc::clone(&self.values));
+        while let Some(item) = current {
+            if let Some(value) = item.borrow_mut().lookup(&name.lexeme) {
+                return Ok(value.clone());
+            }
+            current = item.borrow().parent.clone();
         }
+        Err(RloxError::RuntimeError(format!("Undefined variable '{}'.", name.lexeme)))
     }
 
-    pub fn get_by_depth(&self, name: &Token, depth: usize) -> Result<LoxValue, RloxError> {
-        if name.t_type != TokenType::Identifier && name.t_type != TokenType::This && name.t_type != TokenType::Super {
-            return Err(RloxError::RuntimeError(format!("Invalid token type '{}'.", name.lexeme)));
-        }
-        // go to depth
-        let current = self.ancestor(depth);
-        let res = match current.borrow().table.get(&name.lexeme) {
-            Some(value) => Ok(value.clone()),
-            None => Err(RloxError::RuntimeError(format!("Undefined variable '{}'.", name.lexeme))),
+    pub fn get_resolved(&self, name: &Token, binding: Binding) -> Result<LoxValue, RloxError> {
+        let value = match binding {
+            Binding::Local { depth, slot } => self.ancestor(depth).borrow().slots.get(slot).cloned(),
+            Binding::Global => self.global.borrow().table.get(name.lexeme.as_str()).cloned(),
         };
-        return res;
+        value.ok_or_else(|| RloxError::RuntimeError(format!("Undefined variable '{}'.", name.lexeme)))
     }
 
     fn ancestor(&self, depth: usize) -> Rc<RefCell<EnvItem>> {
@@ -198,6 +226,4 @@ impl Environment {
         }
         current
     }
-    
-    
-}
\ No newline at end of file
+}
@@ -9,7 +9,7 @@ pub enum RloxError {
     LexicalError(usize, String, String),  // line, message, near
     SyntaxError(usize, String, String),   // line, message, near
     RuntimeError(String),  // message
-    SemanticError(String),  // message
+    SemanticError(usize, String),  // line, message
     ReturnValue(LoxValue),  // return value, which is not an error actually
 }
 
@@ -26,12 +26,41 @@ impl fmt::Display for RloxError {
             RloxError::LexicalError(_line, message, near) => write!(f, "Error at '{}': {}.", near, message),
             RloxError::SyntaxError(_line, message, near) => write!(f, "Error: at '{}': {}.", near, message),
             RloxError::RuntimeError(message) => write!(f, "RuntimeError: {}", message),
-            RloxError::SemanticError(message) => write!(f, "Error: {}", message),
+            RloxError::SemanticError(_line, message) => write!(f, "Error: {}", message),
             RloxError::ReturnValue(_) => write!(f, "Uncaught return value."),
         }
     }
 }
 
+impl RloxError {
+    /// The source line an error points at, if it is known.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            RloxError::LexicalError(line, _, _)
+            | RloxError::SyntaxError(line, _, _)
+            | RloxError::SemanticError(line, _) => Some(*line),
+            _ => None,
+        }
+    }
+
+    /// The message of an error without the prefix it is displayed with.
+    pub fn message(&self) -> String {
+        match self {
+            RloxError::LexicalError(_, message, _) | RloxError::SyntaxError(_, message, _) => format!("{}.", message),
+            RloxError::RuntimeError(message) | RloxError::SemanticError(_, message) => message.clone(),
+            _ => self.to_string(),
+        }
+    }
+
+    /// The piece of source text an error was raised near, if it is known.
+    pub fn near(&self) -> Option<&str> {
+        match self {
+            RloxError::LexicalError(_, _, near) | RloxError::SyntaxError(_, _, near) => Some(near),
+            _ => None,
+        }
+    }
+}
+
 pub fn report(e: &RloxError) {
     println!("{}", e);
 }
\ No newline at end of file
@@ -0,0 +1,122 @@
+//! Highlight:
+//! Colours Lox source for terminals (ANSI escape codes) or for web pages
+//! (HTML with CSS classes), using the tokens of the scanner.
+//!
+//! The scanner keeps whitespace, comments and malformed text as tokens,
+//! so the output reproduces the source exactly, errors included.
+
+use std::collections::HashSet;
+
+use crate::ast::token::{Token, TokenType};
+use crate::scanner::{generate_keywords, Scanner};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Ansi,
+    Html,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "ansi" => Some(Format::Ansi),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+}
+
+/// What a token is highlighted as. The name is also its CSS class, `lox-<name>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    Keyword,
+    String,
+    Number,
+    Identifier,
+    Operator,
+    Punctuation,
+    Comment,
+    Error,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Keyword => "keyword",
+            Category::String => "string",
+            Category::Number => "number",
+            Category::Identifier => "identifier",
+            Category::Operator => "operator",
+            Category::Punctuation => "punctuation",
+            Category::Comment => "comment",
+            Category::Error => "error",
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            Category::Keyword => "\x1b[1;35m",
+            Category::String => "\x1b[32m",
+            Category::Number => "\x1b[36m",
+            Category::Identifier | Category::Punctuation => "",
+            Category::Operator => "\x1b[33m",
+            Category::Comment => "\x1b[90m",
+            Category::Error => "\x1b[4;31m",
+        }
+    }
+}
+
+/// Scans `source` with its trivia, so that the lexemes of the tokens add up to the source.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.silent = true;
+    scanner.keep_trivia = true;
+    scanner.scan_tokens()
+}
+
+/// The category of a token, or `None` for whitespace and the end of input.
+pub fn category(t_type: &TokenType, keywords: &HashSet<TokenType>) -> Option<Category> {
+    match t_type {
+        t if keywords.contains(t) => Some(Category::Keyword),
+        TokenType::String | TokenType::StringPart => Some(Category::String),
+        TokenType::Number => Some(Category::Number),
+        TokenType::Identifier => Some(Category::Identifier),
+        TokenType::Comment | TokenType::DocComment => Some(Category::Comment),
+        TokenType::Invalid => Some(Category::Error),
+        TokenType::LeftParen | TokenType::RightParen | TokenType::LeftBrace | TokenType::RightBrace
+        | TokenType::Comma | TokenType::Dot | TokenType::Colon | TokenType::Semicolon => Some(Category::Punctuation),
+        TokenType::Whitespace | TokenType::EOF => None,
+        _ => Some(Category::Operator),
+    }
+}
+
+pub fn highlight(source: &str, format: Format) -> String {
+    let keywords: HashSet<TokenType> = generate_keywords().into_values().collect();
+    let mut output = String::with_capacity(source.len() * 2);
+    if format == Format::Html {
+        output.push_str("<pre class=\"lox\"><code>");
+    }
+    for token in tokenize(source) {
+        let category = category(&token.t_type, &keywords);
+        match (format, category) {
+            (Format::Ansi, Some(category)) if !category.ansi().is_empty() => {
+                output.push_str(category.ansi());
+                output.push_str(&token.lexeme);
+                output.push_str("\x1b[0m");
+            }
+            (Format::Ansi, _) => output.push_str(&token.lexeme),
+            (Format::Html, Some(category)) => {
+                output.push_str(&format!("<span class=\"lox-{}\">{}</span>", category.name(), escape_html(&token.lexeme)));
+            }
+            (Format::Html, None) => output.push_str(&escape_html(&token.lexeme)),
+        }
+    }
+    if format == Format::Html {
+        output.push_str("</code></pre>\n");
+    }
+    output
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
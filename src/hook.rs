@@ -0,0 +1,47 @@
+//! Hooks let tools such as the debugger observe a running `Interpreter`.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ast::stmt::Stmt;
+use crate::error::RloxError;
+use crate::interpreter::Interpreter;
+
+/// Every event has an empty default, so a hook only implements what it needs.
+pub trait Hook {
+    /// Called before each statement that has a source line.
+    /// Returning an error aborts the program with that error.
+    fn before_stmt(&mut self, _interpreter: &mut Interpreter, _stmt: &Stmt, _line: usize) -> Result<(), RloxError> {
+        Ok(())
+    }
+
+    /// Called when a function, method or native function is entered.
+    fn on_call_enter(&mut self, _interpreter: &Interpreter, _name: &str) {}
+
+    /// Called when a call returns, whether normally or with an error.
+    fn on_call_exit(&mut self, _interpreter: &Interpreter, _name: &str) {}
+
+    /// Called when a branch is decided, at the `if`, `while` or `for` keyword or
+    /// at the `and` or `or` operator.
+    /// `arm` is 0 when the body runs or the right operand is evaluated, 1 otherwise.
+    fn on_branch(&mut self, _interpreter: &Interpreter, _line: usize, _column: usize, _arm: usize) {}
+}
+
+/// A shared hook, so that its owner can still read it after the program ran.
+impl<H: Hook> Hook for Rc<RefCell<H>> {
+    fn before_stmt(&mut self, interpreter: &mut Interpreter, stmt: &Stmt, line: usize) -> Result<(), RloxError> {
+        self.borrow_mut().before_stmt(interpreter, stmt, line)
+    }
+
+    fn on_call_enter(&mut self, interpreter: &Interpreter, name: &str) {
+        self.borrow_mut().on_call_enter(interpreter, name)
+    }
+
+    fn on_call_exit(&mut self, interpreter: &Interpreter, name: &str) {
+        self.borrow_mut().on_call_exit(interpreter, name)
+    }
+
+    fn on_branch(&mut self, interpreter: &Interpreter, line: usize, column: usize, arm: usize) {
+        self.borrow_mut().on_branch(interpreter, line, column, arm)
+    }
+}
@@ -0,0 +1,77 @@
+//! Interned identifiers.
+//!
+//! Names of variables, parameters and fields are kept as `Symbol`s. Interning the
+//! same text twice gives the same allocation, so a symbol is cheap to clone and
+//! two symbols compare by pointer.
+
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+
+thread_local! {
+    static SYMBOLS: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+#[derive(Clone, Eq)]
+pub struct Symbol(Rc<str>);
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        SYMBOLS.with(|symbols| {
+            let mut symbols = symbols.borrow_mut();
+            if let Some(symbol) = symbols.get(name) {
+                return Symbol(Rc::clone(symbol));
+            }
+            let symbol: Rc<str> = Rc::from(name);
+            symbols.insert(Rc::clone(&symbol));
+            Symbol(symbol)
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// hashes the text, so that maps keyed by symbols can be searched with a `&str`
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
@@ -1,19 +1,60 @@
 
 use crate::ast::{expr, stmt};
+use crate::ast::expr::NodeId;
 use crate::value::{LoxFunction, LoxValue};
 use crate::class::{LoxClass, LoxInstance};
-use crate::env::Environment;
+use crate::env::{Binding, EnvItem, Environment};
 use crate::ast::token::{Token, TokenType};
 use crate::error::RloxError;
-use crate::builtin::regist_builtins;
+use crate::intern::Symbol;
+use crate::builtin::{self, regist_builtins};
+use crate::hook::Hook;
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// An active call, innermost last in `Interpreter::frames`.
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub name: Symbol,
+    pub line: usize,    // line of the statement being executed in this frame
+}
+
+/// The method a call site found on an instance of `class`, reused while the
+/// site keeps seeing instances of that class.
+#[derive(Debug, Clone)]
+pub struct CallSite {
+    pub class: Rc<RefCell<LoxClass>>,
+    pub name: Symbol,
+    pub method: Rc<LoxFunction>,
+}
+
+/// A `test "name" { ... }` declaration, kept until a test runner asks for it.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    pub name: String,
+    pub line: usize,
+    pub body: Rc<Vec<stmt::Stmt>>,
+    pub closure: Rc<RefCell<EnvItem>>,    // environment the test was declared in
+}
 
 pub struct Interpreter {
     pub had_error: bool,
     pub env: Environment,
-    pub locals: HashMap<Token, usize>,
+    /// What the resolver bound each variable node to, indexed by its `NodeId`.
+    pub locals: Vec<Option<Binding>>,
+    /// The id the parser should give the first node of the next program this runs.
+    pub next_node_id: usize,
+    /// What each method call site last found, indexed by the `NodeId` of the call.
+    pub call_sites: Vec<Option<CallSite>>,
+    /// Where `print` statements and runtime errors are written to.
+    pub output: Box<dyn Write>,
+    pub frames: Vec<CallFrame>,
+    pub hook: Option<Box<dyn Hook>>,
+    /// Line of the innermost statement that raised the last runtime error.
+    pub error_line: Option<usize>,
+    /// Tests declared by the program. They only run when a runner calls `run_test`.
+    pub tests: Vec<TestCase>,
 }
 
 impl Interpreter {
@@ -23,7 +64,14 @@ impl Interpreter {
         Interpreter {
             had_error: false,
             env: environment,
-            locals: HashMap::new(),
+            locals: Vec::new(),
+            next_node_id: 0,
+            call_sites: Vec::new(),
+            output: Box::new(io::stdout()),
+            frames: vec![CallFrame { name: Symbol::intern("<script>"), line: 0 }],
+            hook: None,
+            error_line: None,
+            tests: Vec::new(),
         }
     }
 
@@ -32,18 +80,26 @@ impl Interpreter {
     }
 
     
-    /// Resolves a variable by associating it with a specific depth in the environment.
+    /// Resolves a variable by associating it with where it lives in the environment.
     ///
     /// This function is used during the static analysis phase to record the depth
-    /// at which a variable is located in the environment. The depth is stored in
-    /// the `locals` map, which is later used during runtime to efficiently retrieve
-    /// the variable's value.
+    /// and slot of a local, or that a variable is a global. The binding is stored in
+    /// the `locals` table, which is later used during runtime to retrieve the
+    /// variable's value without looking up its name.
     ///
     /// # Parameters
-    /// - `name`: The `Token` representing the variable's name.
-    /// - `depth`: The depth of the variable in the environment hierarchy.
-    pub fn resolve(&mut self, name: &Token, depth: usize) {
-        self.locals.insert(name.clone(), depth);
+    /// - `id`: The `NodeId` of the expression naming the variable.
+    /// - `binding`: Where the variable is declared.
+    pub fn resolve(&mut self, id: NodeId, binding: Binding) {
+        if self.locals.len() <= id.0 {
+            self.locals.resize(id.0 + 1, None);
+        }
+        self.locals[id.0] = Some(binding);
+    }
+
+    /// Where the resolver found a variable, or `None` if it is looked up by name.
+    fn binding(&self, id: NodeId) -> Option<Binding> {
+        self.locals.get(id.0).copied().flatten()
     }
 
     /// If the program is a valid program, it will be interpreted. <br>
@@ -51,29 +107,160 @@ impl Interpreter {
     /// Otherwise, use visitor pattern, which means stmt.accept(self) <br>
     pub fn interpret(&mut self, program: stmt::Stmt) {
         self.had_error = false;
+        self.error_line = None;
         if let stmt::Stmt::Program(_) = program {
             if let Err(e) = program.accept(self) {
                 self.runtime_error(e);
             }
         } else {
-            println!("Input is not a valid program!");
+            let _ = writeln!(self.output, "Input is not a valid program!");
             self.had_error = true;
         }
     }
 
+    /// Executes a single statement, giving the hook a chance to run first.
+    pub fn execute(&mut self, statement: &stmt::Stmt) -> Result<(), RloxError> {
+        if let Some(line) = statement.line() {
+            if let Some(frame) = self.frames.last_mut() {
+                frame.line = line;
+            }
+            // the hook is taken out while it runs, so that it can use the interpreter itself
+            if let Some(mut hook) = self.hook.take() {
+                let result = hook.before_stmt(self, statement, line);
+                self.hook = Some(hook);
+                result?;
+            }
+            let result = statement.accept(self);
+            // statements that failed further in have already recorded their line
+            if let Err(RloxError::RuntimeError(_)) = result {
+                self.error_line.get_or_insert(line);
+            }
+            return result;
+        }
+        statement.accept(self)
+    }
+
+    /// Calls a function whose arity was already checked, telling the hook about it.
+    pub fn call(&mut self, function: &LoxFunction, arguments: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+        if let Some(mut hook) = self.hook.take() {
+            hook.on_call_enter(self, &function.qualified_name());
+            self.hook = Some(hook);
+        }
+        let result = function.invoke(self, arguments);
+        if let Some(mut hook) = self.hook.take() {
+            hook.on_call_exit(self, &function.qualified_name());
+            self.hook = Some(hook);
+        }
+        result
+    }
+
+    /// Runs a declared test in a fresh scope inside the environment it was declared in.
+    pub fn run_test(&mut self, test: &TestCase) -> Result<(), RloxError> {
+        let global = self.env.global.clone();
+        let env = Environment::from(self.env.call_stack + 1, global, Rc::clone(&test.closure))?;
+        let old_env = self.change_env(env);
+        self.env.enter_scope();
+        self.error_line = None;
+        self.frames.push(CallFrame { name: Symbol::intern(&format!("test \"{}\"", test.name)), line: test.line });
+        let result = self.execute_block(&test.body);
+        self.frames.pop();
+        self.env.exit_scope();
+        self.change_env(old_env);
+        result
+    }
+
+    /// Tells the hook which arm of a branch was taken.
+    fn branch(&mut self, line: usize, column: usize, arm: usize) {
+        if let Some(mut hook) = self.hook.take() {
+            hook.on_branch(self, line, column, arm);
+            self.hook = Some(hook);
+        }
+    }
+
     /// This function is used to execute a block of statements. <br>
     /// Different from the visit_block_stmt function, this function does not enter a new scope. <br>
     /// So it is used to execute a block of statements in the current scope. <br>
     pub fn execute_block(&mut self, block: &Vec<stmt::Stmt>) -> Result<(), RloxError> {
         for statement in block {
-            statement.accept(self)?;
+            self.execute(statement)?;
         }
         Ok(())
     }
 }
 
 impl Interpreter {
-    fn is_truthy(value: &LoxValue) -> bool {
+    /// Calls a function or class with the values of `arguments`.
+    fn call_value(&mut self, callee_value: LoxValue, arguments: &[expr::Expr]) -> Result<LoxValue, RloxError> {
+        if let LoxValue::Callable(method) = callee_value {
+            let mut arg_values = Vec::new();
+            for arg in arguments {
+                arg_values.push(arg.accept(self)?);
+            }
+            if arg_values.len() != method.arity() as usize {
+                return Err(RloxError::RuntimeError(format!("Expected {} arguments but got {}.", method.arity(), arg_values.len())));
+            }
+            self.call(&method, arg_values)
+        } else if let LoxValue::Class(class) = callee_value {
+            let instance = LoxInstance::new(&class);
+            let class = instance.class.clone();
+            let initializer = class.borrow().find_method("init");
+            let instance_rc = Rc::new(RefCell::new(instance));
+            if let Some(initializer) = initializer {
+                let mut arg_values = Vec::new();
+                for arg in arguments {
+                    arg_values.push(arg.accept(self)?);
+                }
+                if arg_values.len() != initializer.arity() as usize {
+                    return Err(RloxError::RuntimeError(format!("Expected {} arguments but got {}.", initializer.arity(), arg_values.len())));
+                }
+                
+                let initializer = initializer.bind(Rc::clone(&instance_rc));
+                self.call(&initializer, arg_values)?;
+            }
+
+            Ok(LoxValue::Instance(Rc::clone(&instance_rc)))
+        } else {
+            Err(RloxError::RuntimeError("Can only call functions and classes.".to_string()))
+        }
+    }
+
+    /// The method a call site finds on an instance, unless a field of that name hides it.
+    /// The last class seen at each call site is remembered with the method it had.
+    fn cached_method(&mut self, id: NodeId, instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Option<Rc<LoxFunction>> {
+        let instance = instance.borrow();
+        if instance.fields().contains_key(name.lexeme.as_str()) {
+            return None;
+        }
+        if let Some(Some(site)) = self.call_sites.get(id.0) {
+            if Rc::ptr_eq(&site.class, &instance.class) && site.name.as_str() == name.lexeme {
+                return Some(Rc::clone(&site.method));
+            }
+        }
+        let method = instance.class.borrow().find_method(&name.lexeme)?;
+        if self.call_sites.len() <= id.0 {
+            self.call_sites.resize(id.0 + 1, None);
+        }
+        self.call_sites[id.0] = Some(CallSite {
+            class: Rc::clone(&instance.class),
+            name: Symbol::intern(&name.lexeme),
+            method: Rc::clone(&method),
+        });
+        Some(method)
+    }
+
+    /// The value of the property `name` of an object.
+    fn property(&mut self, object_value: LoxValue, name: &Token) -> Result<LoxValue, RloxError> {
+        match object_value {
+            LoxValue::Instance(instance) => instance.borrow().get(&name.lexeme, &instance),
+            // strings and lists have native methods, but no fields
+            LoxValue::String(_) | LoxValue::List(_) => builtin::method(&object_value, &name.lexeme)
+                .map(LoxValue::Callable)
+                .ok_or_else(|| RloxError::RuntimeError(format!("Undefined property '{}'.", name.lexeme))),
+            _ => Err(RloxError::RuntimeError("Only instances have properties.".to_string())),
+        }
+    }
+
+    pub fn is_truthy(value: &LoxValue) -> bool {
         match value {
             LoxValue::Boolean(b) => *b,
             LoxValue::Null => false,
@@ -82,7 +269,8 @@ impl Interpreter {
     }
 
     fn runtime_error(&mut self, error: RloxError) {
-        println!("{}", error);
+        self.had_error = true;
+        let _ = writeln!(self.output, "{}", error);
     }
 }
 
@@ -97,7 +285,7 @@ impl expr::Visitor<Result<LoxValue, RloxError>> for Interpreter {
             TokenType::Plus => {
                 match (lv, rv) {
                     (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number(l + r)),
-                    (LoxValue::String(l), LoxValue::String(r)) => Ok(LoxValue::String(format!("{l}{r}"))),
+                    (LoxValue::String(l), LoxValue::String(r)) => Ok(LoxValue::String(format!("{l}{r}").into())),
                     _ => Err(RloxError::RuntimeError("Operands must be two numbers or two strings.".to_string()))
                 }
             }
@@ -126,6 +314,18 @@ impl expr::Visitor<Result<LoxValue, RloxError>> for Interpreter {
                     Err(RloxError::RuntimeError("Operands must be two numbers.".to_string()))
                 }
             }
+            TokenType::Percent => {
+                // the remainder has the sign of the left operand, as in `div`
+                if let (LoxValue::Number(l), LoxValue::Number(r)) = (lv, rv) {
+                    if r == 0.0 {
+                        Err(RloxError::RuntimeError("Division by zero.".to_string()))
+                    } else {
+                        Ok(LoxValue::Number(l % r))
+                    }
+                } else {
+                    Err(RloxError::RuntimeError("Operands must be two numbers.".to_string()))
+                }
+            }
             TokenType::Greater => {
                 if let (LoxValue::Number(l), LoxValue::Number(r)) = (lv, rv) {
                     Ok(LoxValue::Boolean(l > r))
@@ -167,15 +367,19 @@ impl expr::Visitor<Result<LoxValue, RloxError>> for Interpreter {
         match operator.t_type {
             TokenType::Or => {
                 if Interpreter::is_truthy(&lv) {
+                    self.branch(operator.line, operator.column, 1);
                     Ok(lv)
                 } else {
+                    self.branch(operator.line, operator.column, 0);
                     right.accept(self)
                 }
             }
             TokenType::And => {
                 if Interpreter::is_truthy(&lv) {
+                    self.branch(operator.line, operator.column, 0);
                     right.accept(self)
                 } else {
+                    self.branch(operator.line, operator.column, 1);
                     Ok(lv)
                 }
             }
@@ -214,18 +418,18 @@ impl expr::Visitor<Result<LoxValue, RloxError>> for Interpreter {
         
     }
 
-    fn visit_variable_expr(&mut self, name: &Token) -> Result<LoxValue, RloxError> {
-        if let Some(depth) = self.locals.get(name) {
-            self.env.get_by_depth(name, *depth)
+    fn visit_variable_expr(&mut self, name: &Token, id: NodeId) -> Result<LoxValue, RloxError> {
+        if let Some(binding) = self.binding(id) {
+            self.env.get_resolved(name, binding)
         } else {
             self.env.get(name)
         }
     }
 
-    fn visit_assign_expr(&mut self, left: &Token, right: &expr::Expr) -> Result<LoxValue, RloxError> {
+    fn visit_assign_expr(&mut self, left: &Token, right: &expr::Expr, id: NodeId) -> Result<LoxValue, RloxError> {
         let value = right.accept(self)?;
-        if let Some(depth) = self.locals.get(left) {
-            self.env.assign_by_depth(left, value.clone(), *depth)?;
+        if let Some(binding) = self.binding(id) {
+            self.env.assign_resolved(left, value.clone(), binding)?;
             Ok(value)
         } else {
             self.env.assign(left, value.clone())?;
@@ -233,47 +437,26 @@ impl expr::Visitor<Result<LoxValue, RloxError>> for Interpreter {
         }
     }
 
-    fn visit_call_expr(&mut self, callee: &expr::Expr, arguments: &[expr::Expr]) -> Result<LoxValue, RloxError> {
-        let callee_value = callee.accept(self)?;
-        if let LoxValue::Callable(method) = callee_value {
-            let mut arg_values = Vec::new();
-            for arg in arguments {
-                arg_values.push(arg.accept(self)?);
-            }
-            if arg_values.len() != method.arity() as usize {
-                return Err(RloxError::RuntimeError(format!("Expected {} arguments but got {}.", method.arity(), arg_values.len())));
-            }
-            method.invoke(self, arg_values)
-        } else if let LoxValue::Class(class) = callee_value {
-            let instance = LoxInstance::new(&class);
-            let class = instance.class.clone();
-            let initializer = class.borrow().find_method("init");
-            let instance_rc = Rc::new(RefCell::new(instance));
-            if let Some(initializer) = initializer {
-                let mut arg_values = Vec::new();
-                for arg in arguments {
-                    arg_values.push(arg.accept(self)?);
-                }
-                if arg_values.len() != initializer.arity() as usize {
-                    return Err(RloxError::RuntimeError(format!("Expected {} arguments but got {}.", initializer.arity(), arg_values.len())));
+    fn visit_call_expr(&mut self, callee: &expr::Expr, arguments: &[expr::Expr], id: NodeId) -> Result<LoxValue, RloxError> {
+        let callee_value = if let expr::Expr::Get(object, name) = callee {
+            let object_value = object.accept(self)?;
+            // a method called on an instance right away is not turned into a value first
+            if let LoxValue::Instance(instance) = &object_value {
+                if let Some(method) = self.cached_method(id, instance, name) {
+                    let method = LoxFunction::BoundMethod(Rc::clone(instance), method);
+                    return self.call_value(LoxValue::Callable(method), arguments);
                 }
-                
-                initializer.bind(Rc::clone(&instance_rc)).invoke(self, arg_values)?;
             }
-
-            Ok(LoxValue::Instance(Rc::clone(&instance_rc)))
+            self.property(object_value, name)?
         } else {
-            Err(RloxError::RuntimeError("Can only call functions and classes.".to_string()))
-        }
+            callee.accept(self)?
+        };
+        self.call_value(callee_value, arguments)
     }
 
     fn visit_get_expr(&mut self, object: &expr::Expr, name: &Token) -> Result<LoxValue, RloxError> {
         let object_value = object.accept(self)?;
-        if let LoxValue::Instance(instance) = object_value {
-            instance.borrow().get(&name.lexeme, &instance)
-        } else {
-            Err(RloxError::RuntimeError("Only instances have properties.".to_string()))
-        }
+        self.property(object_value, name)
     }
 
     fn visit_set_expr(&mut self, object: &expr::Expr, name: &Token, value: &expr::Expr) -> Result<LoxValue, RloxError> {
@@ -287,21 +470,22 @@ impl expr::Visitor<Result<LoxValue, RloxError>> for Interpreter {
         }
     }
 
-    fn visit_this_expr(&mut self, name: &Token) -> Result<LoxValue, RloxError> {
-        if let Some(depth) = self.locals.get(name) {
-            self.env.get_by_depth(name, *depth)
+    fn visit_this_expr(&mut self, name: &Token, id: NodeId) -> Result<LoxValue, RloxError> {
+        if let Some(binding) = self.binding(id) {
+            self.env.get_resolved(name, binding)
         } else {
             self.env.get(name)
         }
     }
 
-    fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> Result<LoxValue, RloxError> {
-        if let Some(depth) = self.locals.get(keyword) {
-            let super_class = self.env.get_by_depth(keyword, *depth)?;
+    fn visit_super_expr(&mut self, keyword: &Token, method: &Token, id: NodeId) -> Result<LoxValue, RloxError> {
+        if let Some(Binding::Local { depth, slot }) = self.binding(id) {
+            let super_class = self.env.get_resolved(keyword, Binding::Local { depth, slot })?;
             if let LoxValue::Class(super_class) = super_class {
                 if let Some(method) = super_class.borrow().find_method(&method.lexeme) {
                     let this_token = Token::new(TokenType::This, "this".to_string(), 0);
-                    let this_value = self.env.get_by_depth(&this_token, *depth - 1)?;
+                    // `this` is bound in the scope just inside the one holding `super`
+                    let this_value = self.env.get_resolved(&this_token, Binding::Local { depth: depth - 1, slot: 0 })?;
                     if let LoxValue::Instance(instance) = this_value {
                         return Ok(LoxValue::Callable(method.bind(Rc::clone(&instance))));
                     } else {
@@ -317,6 +501,14 @@ impl expr::Visitor<Result<LoxValue, RloxError>> for Interpreter {
             return Err(RloxError::RuntimeError("Can't use 'super' in a class with no superclass.".to_string()));
         }
     }
+
+    fn visit_interpolation_expr(&mut self, parts: &[expr::Expr]) -> Result<LoxValue, RloxError> {
+        let mut result = String::new();
+        for part in parts {
+            result.push_str(&part.accept(self)?.to_string());
+        }
+        Ok(LoxValue::String(result.into()))
+    }
 }
 
 
@@ -331,13 +523,13 @@ impl stmt::Visitor<Result<(), RloxError>> for Interpreter {
 
     fn visit_print_stmt(&mut self, expression: &expr::Expr) -> Result<(), RloxError> {
         let value = expression.accept(self)?;
-        println!("{}", value.to_string());
+        writeln!(self.output, "{}", value.to_string())?;
         Ok(())
     }
 
     fn visit_program_stmt(&mut self, statements: &Vec<stmt::Stmt>) -> Result<(), RloxError> {
         for statement in statements {
-            statement.accept(self)?;
+            self.execute(statement)?;
         }
         Ok(())
     }
@@ -345,7 +537,7 @@ impl stmt::Visitor<Result<(), RloxError>> for Interpreter {
     fn visit_block_stmt(&mut self, statements: &Vec<stmt::Stmt>) -> Result<(), RloxError> {
         self.env.enter_scope();
         for statement in statements {
-            if let Err(e) = statement.accept(self) {
+            if let Err(e) = self.execute(statement) {
                 self.env.exit_scope();
                 return Err(e);
             }
@@ -360,31 +552,37 @@ impl stmt::Visitor<Result<(), RloxError>> for Interpreter {
         } else {
             LoxValue::Null
         };
-        self.env.define(&name.lexeme, value);
+        self.env.define(Symbol::intern(&name.lexeme), value);
         Ok(())
     }
 
-    fn visit_if_stmt(&mut self, condition: &expr::Expr, then_branch: &Box<stmt::Stmt>, else_branch: &Option<Box<stmt::Stmt>>) -> Result<(), RloxError> {
+    fn visit_if_stmt(&mut self, keyword: &Token, condition: &expr::Expr, then_branch: &Box<stmt::Stmt>, else_branch: &Option<Box<stmt::Stmt>>) -> Result<(), RloxError> {
         if Interpreter::is_truthy(&condition.accept(self)?) {
-            then_branch.accept(self)?;
-        } else if let Some(else_branch) = else_branch {
-            else_branch.accept(self)?;
+            self.branch(keyword.line, keyword.column, 0);
+            self.execute(then_branch)?;
+        } else {
+            self.branch(keyword.line, keyword.column, 1);
+            if let Some(else_branch) = else_branch {
+                self.execute(else_branch)?;
+            }
         }
         Ok(())
     }
 
-    fn visit_while_stmt(&mut self, condition: &expr::Expr, body: &Box<stmt::Stmt>) -> Result<(), RloxError> {
+    fn visit_while_stmt(&mut self, keyword: &Token, condition: &expr::Expr, body: &Box<stmt::Stmt>) -> Result<(), RloxError> {
         while Interpreter::is_truthy(&condition.accept(self)?) {
-            body.accept(self)?;
+            self.branch(keyword.line, keyword.column, 0);
+            self.execute(body)?;
         }
+        self.branch(keyword.line, keyword.column, 1);
         Ok(())
     }
 
     fn visit_function_decl_stmt(&mut self, name: &Token, params: &Vec<Token>, body: &Rc<Vec<stmt::Stmt>>) -> Result<(), RloxError> {
         // resolve function name
-        let name = name.lexeme.clone();
+        let name = Symbol::intern(&name.lexeme);
         // resolve names of parameters
-        let params: Vec<String> = params.iter().map(|param| param.lexeme.clone()).collect();
+        let params: Vec<Symbol> = params.iter().map(|param| Symbol::intern(&param.lexeme)).collect();
         // create a new function
         let function = LoxValue::Callable(LoxFunction::UserFunction{
             def_name: name.clone(),
@@ -392,13 +590,24 @@ impl stmt::Visitor<Result<(), RloxError>> for Interpreter {
             body: Rc::clone(body),
             closure: Rc::clone(&self.env.values),
             is_initializer: false,
+            class_name: None,
         });
         // define the function in the current environment
-        self.env.define(&name, function);
+        self.env.define(name, function);
+        Ok(())
+    }
+
+    fn visit_test_stmt(&mut self, keyword: &Token, name: &str, body: &Rc<Vec<stmt::Stmt>>) -> Result<(), RloxError> {
+        self.tests.push(TestCase {
+            name: name.to_string(),
+            line: keyword.line,
+            body: Rc::clone(body),
+            closure: Rc::clone(&self.env.values),
+        });
         Ok(())
     }
 
-    fn visit_return_stmt(&mut self, value: &Option<expr::Expr>) -> Result<(), RloxError> {
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: &Option<expr::Expr>) -> Result<(), RloxError> {
         let value = if let Some(expr) = value {
             expr.accept(self)?
         } else {
@@ -409,34 +618,36 @@ impl stmt::Visitor<Result<(), RloxError>> for Interpreter {
 
     fn visit_class_decl_stmt(&mut self, name: &Token, maybe_super_class: &Option<expr::Expr>, methods: &Vec<stmt::Stmt>) -> Result<(), RloxError> {
         let class_name = name.lexeme.clone();
-        self.env.define(&class_name, LoxValue::Null);
+        self.env.define(Symbol::intern(&class_name), LoxValue::Null);
 
         let mut class = LoxClass::new(class_name.clone());
 
         // set super class
         if let Some(super_class) = maybe_super_class {
             if let LoxValue::Class(super_class) = super_class.accept(self)? {
-                class.super_class = Some(Rc::clone(&super_class));
+                // inherited methods are copied in, and overridden by those declared below
+                class = LoxClass::with_super_class(class_name.clone(), Rc::clone(&super_class));
                 // define super
                 self.env.enter_scope();
-                self.env.define("super", LoxValue::Class(Rc::clone(&super_class)));
+                self.env.define(Symbol::intern("super"), LoxValue::Class(Rc::clone(&super_class)));
             } else {
-                return Err(RloxError::SemanticError("Superclass must be a class".to_string()));
+                return Err(RloxError::SemanticError(name.line, "Superclass must be a class".to_string()));
             }
         }
 
         for method in methods {
-            if let stmt::Stmt::FunctionDecl(name, params, body) = method {
-                let method_name = name.lexeme.clone();
+            if let stmt::Stmt::FunctionDecl(name, params, body, _, _) = method {
+                let method_name = Symbol::intern(&name.lexeme);
                 let function = LoxFunction::UserFunction{
                     def_name: method_name.clone(),
-                    params: params.iter().map(|param| param.lexeme.clone()).collect(),
+                    params: params.iter().map(|param| Symbol::intern(&param.lexeme)).collect(),
                     body: Rc::clone(body),
                     closure: Rc::clone(&self.env.values),
-                    is_initializer: method_name == "init",
+                    is_initializer: method_name.as_str() == "init",
+                    class_name: Some(Symbol::intern(&class_name)),
                 };
                 // eprintln!("clousure: {:?}", Rc::clone(&self.env.values));
-                class.methods.insert(method_name.clone(), function);
+                class.methods.insert(method_name, Rc::new(function));
             }
 
         }
@@ -8,4 +8,21 @@ pub mod interpreter;
 pub mod env;
 pub mod builtin;
 pub mod resolver;
-pub mod class;
\ No newline at end of file
+pub mod class;
+pub mod hook;
+pub mod debugger;
+pub mod symbols;
+pub mod rpc;
+pub mod lsp;
+pub mod dap;
+pub mod profiler;
+pub mod coverage;
+pub mod testing;
+pub mod doc;
+pub mod highlight;
+pub mod lint;
+pub mod checker;
+pub mod optimizer;
+
+pub mod intern;
+pub mod bench;
\ No newline at end of file
@@ -0,0 +1,122 @@
+//! Lint:
+//! Warnings the resolver reports about code that is legal but likely wrong,
+//! and the `// lox-allow: code` comments that disable them.
+//!
+//! A `lox-allow` comment after code on a line applies to that line, and one
+//! on a line of its own applies to the next line. Comments before the first
+//! line of code apply to the whole file. Several codes are separated by commas.
+//!
+//! A `// lox-strict` comment before the first line of code turns on strict
+//! mode, like the `--strict` option does.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::ast::token::TokenType;
+use crate::scanner::Scanner;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedParameter,
+    UnreachableCode,
+    ShadowedLocal,
+    AssignToDeclaration,
+    SelfComparison,
+    ConstantCondition,
+    EmptyBlock,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 8] = [
+        Lint::UnusedVariable,
+        Lint::UnusedParameter,
+        Lint::UnreachableCode,
+        Lint::ShadowedLocal,
+        Lint::AssignToDeclaration,
+        Lint::SelfComparison,
+        Lint::ConstantCondition,
+        Lint::EmptyBlock,
+    ];
+
+    /// The stable name used in reports and `lox-allow` comments.
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnusedParameter => "unused-parameter",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::ShadowedLocal => "shadowed-local",
+            Lint::AssignToDeclaration => "assign-to-declaration",
+            Lint::SelfComparison => "self-comparison",
+            Lint::ConstantCondition => "constant-condition",
+            Lint::EmptyBlock => "empty-block",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.code() == code)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub lint: Lint,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Warning at line {}: {} [{}]", self.line, self.message, self.lint.code())
+    }
+}
+
+/// The lints disabled by the `lox-allow` comments of one source file.
+#[derive(Debug, Clone, Default)]
+pub struct Allows {
+    file: HashSet<Lint>,
+    lines: HashMap<usize, HashSet<Lint>>,
+}
+
+impl Allows {
+    pub fn from_source(source: &str) -> Allows {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.silent = true;
+        scanner.keep_trivia = true;
+
+        let mut allows = Allows::default();
+        let mut code_line = None;   // the last line that had code on it
+        for token in scanner.scan_tokens() {
+            match token.t_type {
+                TokenType::Whitespace | TokenType::DocComment | TokenType::Invalid | TokenType::EOF => {}
+                TokenType::Comment => {
+                    let Some(codes) = token.lexeme.trim_start_matches('/').trim().strip_prefix("lox-allow:") else {
+                        continue;
+                    };
+                    let lints = codes.split(',').filter_map(|code| Lint::from_code(code.trim()));
+                    match code_line {
+                        None => allows.file.extend(lints),
+                        Some(line) if line == token.line => allows.lines.entry(line).or_default().extend(lints),
+                        Some(_) => allows.lines.entry(token.line + 1).or_default().extend(lints),
+                    }
+                }
+                _ => code_line = Some(token.line + token.lexeme.matches('\n').count()),
+            }
+        }
+        allows
+    }
+
+    pub fn allows(&self, lint: Lint, line: usize) -> bool {
+        self.file.contains(&lint) || self.lines.get(&line).is_some_and(|lints| lints.contains(&lint))
+    }
+}
+
+/// Whether the source asks for strict mode with a `// lox-strict` comment before any code.
+pub fn strict_pragma(source: &str) -> bool {
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.silent = true;
+    scanner.keep_trivia = true;
+    scanner.scan_tokens().iter()
+        .take_while(|token| matches!(token.t_type, TokenType::Whitespace | TokenType::Comment | TokenType::DocComment))
+        .any(|token| token.t_type == TokenType::Comment && token.lexeme.trim_start_matches('/').trim() == "lox-strict")
+}
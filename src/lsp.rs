@@ -0,0 +1,398 @@
+//! Language server:
+//! Speaks the Language Server Protocol over stdio, so that editors get
+//! diagnostics, navigation, hover and completion for Lox files.
+//!
+//! Every request re-analyzes the whole document with `Scanner`, `Parser`
+//! and `Resolver`. Nothing is printed: errors are collected silently and
+//! published as diagnostics instead.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use serde_json::{json, Value};
+
+use crate::checker::Checker;
+use crate::error::RloxError;
+use crate::interpreter::Interpreter;
+use crate::lint::{strict_pragma, Allows};
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::rpc::{read_message, write_message};
+use crate::scanner::{generate_keywords, Scanner};
+use crate::symbols::{Symbol, SymbolIndex, SymbolKind};
+use crate::value::LoxValue;
+
+/// A problem found in a document. Lines and columns are 1-based.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub start_column: usize,
+    pub end_column: usize,
+    pub message: String,
+    /// 1 for errors and 2 for warnings, as in the protocol.
+    pub severity: u8,
+    /// The lint that raised a warning.
+    pub code: Option<&'static str>,
+}
+
+/// Everything the server knows about one version of a document.
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    pub symbols: SymbolIndex,
+}
+
+pub fn analyze(source: &str) -> Analysis {
+    let mut errors: Vec<RloxError> = Vec::new();
+
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.silent = true;
+    let tokens = scanner.scan_tokens();
+    errors.append(&mut scanner.errors);
+
+    let mut parser = Parser::new(tokens);
+    parser.silent = true;
+    let program = parser.parse();
+    errors.append(&mut parser.errors);
+
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::new(&mut interpreter);
+    resolver.silent = true;
+    resolver.allows = Allows::from_source(source);
+    resolver.strict = strict_pragma(source);
+    if let Some(program) = &program {
+        resolver.resolve_program(program);
+    }
+    errors.append(&mut resolver.errors);
+    if let (Some(program), true) = (&program, errors.is_empty()) {
+        let mut checker = Checker::new();
+        checker.silent = true;
+        checker.check_program(program);
+        errors.append(&mut checker.errors);
+    }
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut diagnostics: Vec<Diagnostic> = errors.iter()
+        .map(|error| {
+            let line = error.line().unwrap_or(1).max(1);
+            let text = lines.get(line - 1).copied().unwrap_or("");
+            let (start_column, end_column) = match error.near().and_then(|near| {
+                // columns count characters, like the scanner's
+                text.find(near).filter(|_| !near.is_empty())
+                    .map(|at| (text[..at].chars().count(), near.chars().count()))
+            }) {
+                Some((at, len)) => (at + 1, at + 1 + len),
+                None => (1, text.chars().count() + 1),
+            };
+            Diagnostic { line, start_column, end_column, message: error.to_string(), severity: 1, code: None }
+        })
+        .collect();
+    for warning in &resolver.warnings {
+        let text = lines.get(warning.line.max(1) - 1).copied().unwrap_or("");
+        let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+        diagnostics.push(Diagnostic {
+            line: warning.line.max(1),
+            start_column: indent + 1,
+            end_column: text.chars().count() + 1,
+            message: warning.message.clone(),
+            severity: 2,
+            code: Some(warning.lint.code()),
+        });
+    }
+
+    Analysis {
+        diagnostics,
+        symbols: std::mem::take(&mut resolver.symbols),
+    }
+}
+
+/// Runs a server until the client sends `exit` or closes the input.
+pub fn run_server<R: BufRead, W: Write>(mut input: R, mut output: W) -> io::Result<()> {
+    let mut server = LanguageServer::new();
+    while let Some(message) = read_message(&mut input)? {
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    Ok(())
+}
+
+pub struct LanguageServer {
+    documents: HashMap<String, String>,
+    exited: bool,
+}
+
+impl Default for LanguageServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LanguageServer {
+    pub fn new() -> Self {
+        LanguageServer {
+            documents: HashMap::new(),
+            exited: false,
+        }
+    }
+
+    /// Handles one incoming message and returns the messages to send back.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = message.get("id").cloned();
+
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "rlox" },
+            })),
+            "shutdown" => Some(Value::Null),
+            "exit" => {
+                self.exited = true;
+                return vec![];
+            }
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                let text = params["textDocument"]["text"].as_str().unwrap_or("").to_string();
+                self.documents.insert(uri.clone(), text);
+                return vec![self.publish_diagnostics(&uri)];
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                // full document sync: the last change holds the whole text
+                if let Some(text) = params["contentChanges"].as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str()) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                return vec![self.publish_diagnostics(&uri)];
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                self.documents.remove(&uri);
+                return vec![notification("textDocument/publishDiagnostics", json!({
+                    "uri": uri,
+                    "diagnostics": [],
+                }))];
+            }
+            "textDocument/definition" => self.with_symbol(params, |uri, index, id| {
+                location(uri, &index.symbols[id])
+            }),
+            "textDocument/references" => self.with_symbol(params, |uri, index, id| {
+                let mut locations = vec![];
+                if params["context"]["includeDeclaration"].as_bool().unwrap_or(true) {
+                    locations.push(location(uri, &index.symbols[id]));
+                }
+                for reference in index.references_to(id) {
+                    locations.push(json!({
+                        "uri": uri,
+                        "range": range(reference.line, reference.column, reference.name.chars().count()),
+                    }));
+                }
+                Value::Array(locations)
+            }),
+            "textDocument/hover" => self.with_symbol(params, |_, index, id| {
+                json!({
+                    "contents": { "kind": "markdown", "value": hover_text(index, id) },
+                })
+            }),
+            "textDocument/completion" => Some(self.completion(params)),
+            "textDocument/documentSymbol" => Some(self.document_symbols(params)),
+            _ => {
+                if let Some(id) = id {
+                    return vec![json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": format!("Unknown method '{}'", method) },
+                    })];
+                }
+                return vec![];
+            }
+        };
+
+        match id {
+            Some(id) => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result.unwrap_or(Value::Null),
+            })],
+            None => vec![],
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Value {
+        let source = self.documents.get(uri).map(String::as_str).unwrap_or("");
+        let diagnostics: Vec<Value> = analyze(source).diagnostics.iter()
+            .map(|d| json!({
+                "range": {
+                    "start": { "line": d.line - 1, "character": d.start_column - 1 },
+                    "end": { "line": d.line - 1, "character": d.end_column - 1 },
+                },
+                "severity": d.severity,
+                "code": d.code,
+                "source": "rlox",
+                "message": d.message,
+            }))
+            .collect();
+        notification("textDocument/publishDiagnostics", json!({
+            "uri": uri,
+            "diagnostics": diagnostics,
+        }))
+    }
+
+    /// Analyzes the document named in `params` and runs `f` on the symbol under the cursor.
+    fn with_symbol<F>(&self, params: &Value, f: F) -> Option<Value>
+    where F: Fn(&str, &SymbolIndex, usize) -> Value,
+    {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let source = self.documents.get(uri)?;
+        let (line, column) = position(params)?;
+        let analysis = analyze(source);
+        let id = analysis.symbols.symbol_at(line, column)?;
+        Some(f(uri, &analysis.symbols, id))
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let mut items: Vec<Value> = vec![];
+        let mut seen: Vec<String> = vec![];
+        let mut push = |label: String, kind: u32, items: &mut Vec<Value>| {
+            if !seen.contains(&label) {
+                items.push(json!({ "label": label, "kind": kind }));
+                seen.push(label);
+            }
+        };
+
+        let source = params["textDocument"]["uri"].as_str()
+            .and_then(|uri| self.documents.get(uri));
+        if let (Some(source), Some((line, _))) = (source, position(params)) {
+            let analysis = analyze(source);
+            let mut visible = analysis.symbols.visible_at(line);
+            // innermost declarations first
+            visible.reverse();
+            for symbol in visible {
+                push(symbol.name.clone(), completion_kind(symbol.kind), &mut items);
+            }
+        }
+
+        let interpreter = Interpreter::new();
+        let mut builtins: Vec<(String, LoxValue)> = interpreter.env.global.borrow().table.iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        builtins.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, value) in builtins {
+            // constants such as PI are numbers, everything else is a function
+            let kind = if matches!(value, LoxValue::Number(_)) { 21 } else { 3 };
+            push(name, kind, &mut items);
+        }
+
+        let mut keywords: Vec<String> = generate_keywords().into_keys().collect();
+        keywords.sort();
+        for keyword in keywords {
+            push(keyword, 14, &mut items);
+        }
+        Value::Array(items)
+    }
+
+    fn document_symbols(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+        let Some(source) = self.documents.get(uri) else {
+            return Value::Array(vec![]);
+        };
+        let analysis = analyze(source);
+        let symbols = analysis.symbols.top_level().into_iter()
+            .map(|symbol| {
+                let mut info = json!({
+                    "name": symbol.name,
+                    "kind": symbol_kind(symbol.kind),
+                    "location": location(uri, symbol),
+                });
+                if let Some(container) = &symbol.container {
+                    info["containerName"] = json!(container);
+                }
+                info
+            })
+            .collect();
+        Value::Array(symbols)
+    }
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+/// Converts an LSP position (0-based) into a 1-based line and column.
+fn position(params: &Value) -> Option<(usize, usize)> {
+    let line = params["position"]["line"].as_u64()? as usize;
+    let character = params["position"]["character"].as_u64()? as usize;
+    Some((line + 1, character + 1))
+}
+
+fn range(line: usize, column: usize, len: usize) -> Value {
+    let line = line.saturating_sub(1);
+    let start = column.saturating_sub(1);
+    json!({
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": start + len },
+    })
+}
+
+fn location(uri: &str, symbol: &Symbol) -> Value {
+    json!({ "uri": uri, "range": range(symbol.line, symbol.column, symbol.name.chars().count()) })
+}
+
+fn hover_text(index: &SymbolIndex, id: usize) -> String {
+    let symbol = &index.symbols[id];
+    let signature = |name: &str, params: &Vec<String>| {
+        format!("{}({})\n```\nTakes {} argument{}.", name, params.join(", "), params.len(),
+            if params.len() == 1 { "" } else { "s" })
+    };
+    match symbol.kind {
+        SymbolKind::Function => format!("```lox\nfun {}", signature(&symbol.name, &symbol.params)),
+        SymbolKind::Method => {
+            let class = symbol.container.clone().unwrap_or_default();
+            format!("```lox\n{}.{}", class, signature(&symbol.name, &symbol.params))
+        }
+        SymbolKind::Class => {
+            let mut text = format!("```lox\nclass {}", symbol.name);
+            if let Some(super_class) = &symbol.super_class {
+                text.push_str(&format!(" < {}", super_class));
+            }
+            text.push_str("\n```");
+            let init = index.symbols.iter().find(|s| {
+                s.kind == SymbolKind::Method && s.name == "init" && s.container.as_ref() == Some(&symbol.name)
+            });
+            let arity = init.map_or(0, |init| init.params.len());
+            text.push_str(&format!("\nConstructor takes {} argument{}.", arity, if arity == 1 { "" } else { "s" }));
+            text
+        }
+        SymbolKind::Variable => format!("```lox\nvar {}\n```", symbol.name),
+        SymbolKind::Parameter => format!("```lox\n{}\n```\nParameter.", symbol.name),
+    }
+}
+
+fn completion_kind(kind: SymbolKind) -> u32 {
+    match kind {
+        SymbolKind::Variable | SymbolKind::Parameter => 6,
+        SymbolKind::Function => 3,
+        SymbolKind::Class => 7,
+        SymbolKind::Method => 2,
+    }
+}
+
+fn symbol_kind(kind: SymbolKind) -> u32 {
+    match kind {
+        SymbolKind::Variable | SymbolKind::Parameter => 13,
+        SymbolKind::Function => 12,
+        SymbolKind::Class => 5,
+        SymbolKind::Method => 6,
+    }
+}
@@ -1,17 +1,137 @@
-use rlox::runner;
+use std::path::PathBuf;
+
+use rlox::{bench, dap, doc, highlight, lsp, runner, testing};
+
+// counts allocations for `rlox bench`, at the cost of an atomic add per allocation
+#[cfg(feature = "bench")]
+#[global_allocator]
+static ALLOCATOR: bench::CountingAllocator = bench::CountingAllocator;
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut options = runner::Options::default();
+    let args: Vec<String> = std::env::args().filter(|arg| !options.parse_flag(arg)).collect();
+    // a script whose warnings are denied exits with EX_DATAERR
+    let compiled = |result: Result<bool, _>| {
+        if !result.unwrap() {
+            std::process::exit(65);
+        }
+    };
     match args.len() {
+        n if n >= 3 && args[1] == "test" => {
+            let bless = args[2..].iter().any(|arg| arg == "--bless");
+            let paths: Vec<PathBuf> = args[2..].iter().filter(|arg| *arg != "--bless").map(PathBuf::from).collect();
+            if !testing::run_suite(&paths, bless, std::io::stdout()).unwrap() {
+                std::process::exit(1);
+            }
+        }
+        n if n >= 3 && args[1] == "doc" => {
+            let mut source = None;
+            let mut out = PathBuf::from("doc");
+            let mut format = doc::Format::Markdown;
+            let mut rest = args[2..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "-o" => out = rest.next().map(PathBuf::from).unwrap_or(out),
+                    "--format" => match rest.next().and_then(|name| doc::Format::from_name(name)) {
+                        Some(f) => format = f,
+                        None => {
+                            eprintln!("--format must be 'markdown' or 'html'");
+                            std::process::exit(64);
+                        }
+                    },
+                    _ => source = Some(PathBuf::from(arg)),
+                }
+            }
+            let Some(source) = source else {
+                eprintln!("Usage: rlox doc <path> [-o <directory>] [--format markdown|html]");
+                std::process::exit(64);
+            };
+            if !doc::generate(&source, &out, format, std::io::stdout()).unwrap() {
+                std::process::exit(1);
+            }
+        }
+        n if n >= 2 && args[1] == "bench" => {
+            if !cfg!(feature = "bench") {
+                eprintln!("rlox bench needs allocation counting: build with `cargo build --release --features bench`");
+                std::process::exit(64);
+            }
+            let mut dir = PathBuf::from("bench");
+            let mut bench_options = bench::BenchOptions::default();
+            let mut rest = args[2..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--runs" => bench_options.runs = rest.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| bench_usage()),
+                    "--baseline" => bench_options.baseline = Some(rest.next().map(PathBuf::from).unwrap_or_else(|| bench_usage())),
+                    "--save" => bench_options.save = Some(rest.next().map(PathBuf::from).unwrap_or_else(|| bench_usage())),
+                    "--threshold" => bench_options.threshold = rest.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| bench_usage()),
+                    _ if arg.starts_with('-') => bench_usage(),
+                    _ => dir = PathBuf::from(arg),
+                }
+            }
+            match bench::run(&dir, &bench_options, std::io::stdout()) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+            }
+        }
+        3 | 5 if args[1] == "highlight" => {
+            let format = match args.len() {
+                5 if args[3] == "--format" => highlight::Format::from_name(&args[4]),
+                5 => None,
+                _ => Some(highlight::Format::Ansi),
+            };
+            let Some(format) = format else {
+                eprintln!("Usage: rlox highlight <script> [--format ansi|html]");
+                std::process::exit(64);
+            };
+            runner::highlight_file(&args[2], format).unwrap();
+        }
         1 => {
             runner::run_prompt().unwrap();
         }
+        2 if args[1] == "lsp" => {
+            let stdin = std::io::stdin();
+            lsp::run_server(stdin.lock(), std::io::stdout()).unwrap();
+        }
+        2 if args[1] == "dap" => {
+            let stdin = std::io::stdin();
+            dap::run_adapter(stdin.lock(), std::io::stdout()).unwrap();
+        }
         2 => {
-            runner::run_file(&args[1]).unwrap();
+            compiled(runner::run_file(&args[1], &options));
+        }
+        3 if args[1] == "run" => {
+            compiled(runner::run_file(&args[2], &options));
+        }
+        3 if args[1] == "debug" => {
+            compiled(runner::debug_file(&args[2], &options));
+        }
+        4 if args[1] == "--profile" => {
+            compiled(runner::profile_file(&args[3], &args[2], &options));
+        }
+        5 if args[1] == "run" && args[2] == "--coverage" => {
+            compiled(runner::coverage_file(&args[4], &args[3], &options));
         }
         _ => {
-            eprintln!("Usage: rlox [script]");
+            eprintln!("Usage: rlox [--deny-warnings] [--strict] [-O] [script]");
+            eprintln!("       rlox lsp");
+            eprintln!("       rlox dap");
+            eprintln!("       rlox run [--deny-warnings] [--strict] [-O] [--coverage <output.lcov>] <script>");
+            eprintln!("       rlox debug <script>");
+            eprintln!("       rlox test [--bless] <path>...");
+            eprintln!("       rlox highlight <script> [--format ansi|html]");
+            eprintln!("       rlox doc <path> [-o <directory>] [--format markdown|html]");
+            eprintln!("       rlox bench [<directory>] [--runs N] [--baseline <file>] [--save <file>] [--threshold <percent>]");
+            eprintln!("       rlox --profile <output.folded> <script>");
             std::process::exit(64);
         }
     }
 }
+
+fn bench_usage() -> ! {
+    eprintln!("Usage: rlox bench [<directory>] [--runs N] [--baseline <file>] [--save <file>] [--threshold <percent>]");
+    std::process::exit(64);
+}
@@ -0,0 +1,190 @@
+//! Optimizer:
+//! An optional AST-to-AST pass, run after the resolver and the checker.
+//!
+//! It folds operators whose operands are literals, short-circuits `and` and
+//! `or` with a literal on the left, prunes branches and loops whose condition
+//! is a literal, and drops the statements after an unconditional `return`.
+//!
+//! Folding evaluates the operator with the interpreter itself, so folded values
+//! are exactly what the program would compute. Operators that fail, such as
+//! `1 / 0` or `-"a"`, are left in place to raise their error when they run.
+//! Variables keep their tokens, so what the resolver bound them to still holds.
+
+use std::rc::Rc;
+
+use crate::ast::expr::{Expr, LiteralValue};
+use crate::ast::stmt::Stmt;
+use crate::ast::token::TokenType;
+use crate::interpreter::Interpreter;
+use crate::value::LoxValue;
+
+pub struct Optimizer {
+    /// Evaluates folded operators. It never sees anything but literals.
+    interpreter: Interpreter,
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Optimizer { interpreter: Interpreter::new() }
+    }
+
+    pub fn optimize(&mut self, program: Stmt) -> Stmt {
+        match program {
+            Stmt::Program(stmts) => Stmt::Program(self.optimize_stmts(stmts)),
+            stmt => self.optimize_stmt(stmt).unwrap_or(Stmt::Block(vec![])),
+        }
+    }
+
+    /// Optimizes a list of statements, dropping pruned ones and those after an
+    /// unconditional `return`.
+    fn optimize_stmts(&mut self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+        let mut optimized = Vec::with_capacity(stmts.len());
+        for stmt in stmts {
+            if let Some(stmt) = self.optimize_stmt(stmt) {
+                let returns = always_returns(&stmt);
+                optimized.push(stmt);
+                if returns {
+                    break;
+                }
+            }
+        }
+        optimized
+    }
+
+    /// Optimizes a statement, or returns `None` if it does nothing.
+    fn optimize_stmt(&mut self, stmt: Stmt) -> Option<Stmt> {
+        let stmt = match stmt {
+            Stmt::Program(stmts) => Stmt::Program(self.optimize_stmts(stmts)),
+            Stmt::Block(stmts) => Stmt::Block(self.optimize_stmts(stmts)),
+            Stmt::Expression(expression, line) => Stmt::Expression(self.optimize_expr(expression), line),
+            Stmt::Print(expression, line) => Stmt::Print(self.optimize_expr(expression), line),
+            Stmt::Var(name, initializer, annotation) => {
+                Stmt::Var(name, initializer.map(|initializer| self.optimize_expr(initializer)), annotation)
+            }
+            Stmt::Return(keyword, value) => Stmt::Return(keyword, value.map(|value| self.optimize_expr(value))),
+            Stmt::If(condition, then_branch, else_branch, keyword) => {
+                let condition = self.optimize_expr(condition);
+                if let Some(truthy) = literal_truthiness(&condition) {
+                    let branch = if truthy { Some(then_branch) } else { else_branch };
+                    return branch.and_then(|branch| self.optimize_stmt(*branch));
+                }
+                let then_branch = self.optimize_branch(*then_branch);
+                let else_branch = else_branch.and_then(|branch| self.optimize_stmt(*branch)).map(Box::new);
+                Stmt::If(condition, then_branch, else_branch, keyword)
+            }
+            Stmt::While(condition, body, keyword) => {
+                let condition = self.optimize_expr(condition);
+                if literal_truthiness(&condition) == Some(false) {
+                    return None;
+                }
+                Stmt::While(condition, self.optimize_branch(*body), keyword)
+            }
+            Stmt::FunctionDecl(name, params, body, doc, signature) => {
+                let body = self.optimize_stmts(Rc::unwrap_or_clone(body));
+                Stmt::FunctionDecl(name, params, Rc::new(body), doc, signature)
+            }
+            Stmt::ClassDecl(name, super_class, methods, doc) => {
+                Stmt::ClassDecl(name, super_class, self.optimize_stmts(methods), doc)
+            }
+            Stmt::Test(keyword, name, body) => {
+                Stmt::Test(keyword, name, Rc::new(self.optimize_stmts(Rc::unwrap_or_clone(body))))
+            }
+        };
+        Some(stmt)
+    }
+
+    /// Optimizes the body of an `if` or `while`, which must stay a statement.
+    fn optimize_branch(&mut self, stmt: Stmt) -> Box<Stmt> {
+        Box::new(self.optimize_stmt(stmt).unwrap_or(Stmt::Block(vec![])))
+    }
+
+    fn optimize_expr(&mut self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Grouping(expression) => match self.optimize_expr(*expression) {
+                literal @ Expr::Literal(_) => literal,
+                expression => Expr::Grouping(Box::new(expression)),
+            },
+            Expr::Unary(operator, right) => {
+                let folded = Expr::Unary(operator, Box::new(self.optimize_expr(*right)));
+                self.fold(folded)
+            }
+            Expr::Binary(left, operator, right) => {
+                let left = self.optimize_expr(*left);
+                let right = self.optimize_expr(*right);
+                self.fold(Expr::Binary(Box::new(left), operator, Box::new(right)))
+            }
+            Expr::Logical(left, operator, right) => {
+                let left = self.optimize_expr(*left);
+                let right = self.optimize_expr(*right);
+                // `and` and `or` give their left operand if it decides the result
+                match literal_truthiness(&left) {
+                    Some(truthy) if truthy == (operator.t_type == TokenType::Or) => left,
+                    Some(_) => right,
+                    None => Expr::Logical(Box::new(left), operator, Box::new(right)),
+                }
+            }
+            Expr::Interpolation(parts) => {
+                let parts = parts.into_iter().map(|part| self.optimize_expr(part)).collect();
+                self.fold(Expr::Interpolation(parts))
+            }
+            Expr::Assign(name, value, id) => Expr::Assign(name, Box::new(self.optimize_expr(*value)), id),
+            Expr::Call(callee, arguments, line, id) => {
+                let callee = self.optimize_expr(*callee);
+                let arguments = arguments.into_iter().map(|argument| self.optimize_expr(argument)).collect();
+                Expr::Call(Box::new(callee), arguments, line, id)
+            }
+            Expr::Get(object, name) => Expr::Get(Box::new(self.optimize_expr(*object)), name),
+            Expr::Set(object, name, value) => {
+                Expr::Set(Box::new(self.optimize_expr(*object)), name, Box::new(self.optimize_expr(*value)))
+            }
+            expr @ (Expr::Literal(_) | Expr::Variable(..) | Expr::This(..) | Expr::Super(..)) => expr,
+        }
+    }
+
+    /// Replaces an operator whose operands are all literals with its value,
+    /// unless evaluating it is an error.
+    fn fold(&mut self, expr: Expr) -> Expr {
+        let operands_are_literals = match &expr {
+            Expr::Unary(_, right) => matches!(**right, Expr::Literal(_)),
+            Expr::Binary(left, _, right) => matches!((&**left, &**right), (Expr::Literal(_), Expr::Literal(_))),
+            Expr::Interpolation(parts) => parts.iter().all(|part| matches!(part, Expr::Literal(_))),
+            _ => false,
+        };
+        if !operands_are_literals {
+            return expr;
+        }
+        let literal = match expr.accept(&mut self.interpreter) {
+            Ok(LoxValue::Number(n)) => LiteralValue::Number(n),
+            Ok(LoxValue::String(s)) => LiteralValue::String(s),
+            Ok(LoxValue::Boolean(b)) => LiteralValue::Boolean(b),
+            Ok(LoxValue::Null) => LiteralValue::Nil,
+            _ => return expr,
+        };
+        Expr::Literal(literal)
+    }
+}
+
+/// Whether a literal is truthy, or `None` if the expression is not a literal.
+fn literal_truthiness(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Literal(LiteralValue::Nil | LiteralValue::Boolean(false)) => Some(false),
+        Expr::Literal(_) => Some(true),
+        _ => None,
+    }
+}
+
+/// Whether running a statement always ends in a `return`.
+fn always_returns(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(..) => true,
+        Stmt::Block(stmts) => stmts.iter().any(always_returns),
+        Stmt::If(_, then_branch, Some(else_branch), _) => always_returns(then_branch) && always_returns(else_branch),
+        _ => false,
+    }
+}
@@ -1,29 +1,66 @@
 //! This module contains the parser for the language.
 
+use std::collections::HashMap;
 use std::vec;
 use std::rc::Rc;
 
 use crate::ast::{token::*, expr::*, stmt::*};
 use crate::error::{RloxError, report};
-use unescape::unescape;
+use crate::scanner::parse_number;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /// Doc comments by the index of the token that follows them.
+    docs: HashMap<usize, String>,
     pub had_error: bool,
+    /// Errors met while parsing, kept for tools that report them on their own.
+    pub errors: Vec<RloxError>,
+    /// If set, errors are only collected into `errors` and not printed.
+    pub silent: bool,
+    /// The id of the next node that names a variable or makes a call. Programs run by the same
+    /// interpreter must not share ids, so each starts where the last one stopped.
+    pub next_id: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
+        // trivia and doc comments are taken out of the token stream, so that the grammar need not know them
+        let mut docs: HashMap<usize, String> = HashMap::new();
+        let mut code = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            if matches!(token.t_type, TokenType::Whitespace | TokenType::Comment | TokenType::Invalid) {
+                continue;
+            } else if token.t_type == TokenType::DocComment {
+                let text = token.lexeme.trim_start_matches('/');
+                let line = text.strip_prefix(' ').unwrap_or(text).trim_end();
+                let doc = docs.entry(code.len()).or_default();
+                if !doc.is_empty() {
+                    doc.push('\n');
+                }
+                doc.push_str(line);
+            } else {
+                code.push(token);
+            }
+        }
         Parser {
-            tokens,
+            tokens: code,
             current: 0,
+            docs,
             had_error: false,
+            errors: Vec::new(),
+            silent: false,
+            next_id: 0,
         }
     }
 }
 
 impl Parser {
+    fn node_id(&mut self) -> NodeId {
+        self.next_id += 1;
+        NodeId(self.next_id - 1)
+    }
+
     pub fn parse_expr(&mut self) -> Option<Expr> {
         match self.expression() {
             Ok(expr) => Some(expr),
@@ -50,14 +87,33 @@ impl Parser {
     }
 
     fn error(&mut self, message: &str) -> RloxError {
+        let line = self.peek().line;
+        let near = self.peek().lexeme.clone();
+        self.report(RloxError::SyntaxError(line, message.to_string(), near.clone()));
+        RloxError::SyntaxError(line, message.to_string(), near)
+    }
+
+    fn report(&mut self, error: RloxError) {
         self.had_error = true;
-        let error = RloxError::SyntaxError(
-            self.peek().line,
-            message.to_string(),
-            self.peek().lexeme.clone(),
-        );
-        report(&error);
-        error
+        if !self.silent {
+            report(&error);
+        }
+        self.errors.push(error);
+    }
+
+    /// Resolves the escape sequences in the text of a string literal.
+    fn string_text(&mut self, text: &str) -> String {
+        match unescape(text) {
+            Ok(unescaped) => unescaped,
+            Err(sequence) => {
+                self.report(RloxError::LexicalError(
+                    self.previous().line,
+                    "Invalid string escape sequence".to_string(),
+                    sequence,
+                ));
+                text.to_string()
+            }
+        }
     }
 
     fn consume(&mut self, t: TokenType, message: &str) -> Result<&Token, RloxError> {
@@ -131,8 +187,8 @@ impl Parser {
         if self.match_token(vec![TokenType::Equal]) {
             let value = self.assignment()?;
             match expr {
-                Expr::Variable(name) => {
-                    return Ok(Expr::Assign(name, Box::new(value)));
+                Expr::Variable(name, id) => {
+                    return Ok(Expr::Assign(name, Box::new(value), id));
                 }
                 Expr::Get(object, name) => {
                     return Ok(Expr::Set(object, name, Box::new(value)));
@@ -210,7 +266,7 @@ impl Parser {
     fn factor(&mut self) -> Result<Expr, RloxError> {
         let mut expr = self.unary()?;
 
-        while self.match_token(vec![TokenType::Slash, TokenType::Star]) {
+        while self.match_token(vec![TokenType::Slash, TokenType::Star, TokenType::Percent]) {
             let operator = self.previous().clone();
             let right = self.unary()?;
             expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
@@ -264,7 +320,8 @@ impl Parser {
         }
 
         self.consume(TokenType::RightParen, "Expect ')' after arguments")?;
-        Ok(Expr::Call(Box::new(callee), arguments, self.previous().line))
+        let line = self.previous().line;
+        Ok(Expr::Call(Box::new(callee), arguments, line, self.node_id()))
     }
 
     fn primary(&mut self) -> Result<Expr, RloxError> {
@@ -283,34 +340,38 @@ impl Parser {
             },
             TokenType::Identifier => {
                 let name = self.advance().clone();
-                Ok(Expr::Variable(name))
+                Ok(Expr::Variable(name, self.node_id()))
             },
             TokenType::Number => {
                 let lexeme = self.peek().lexeme.clone();
-                let number = lexeme.parse::<f64>().unwrap();
+                let number = parse_number(&lexeme).unwrap();
                 self.advance();
                 Ok(Expr::Literal(LiteralValue::Number(number)))
             },
             TokenType::String => {
-                let lexeme = self.peek().lexeme.clone();
-                let string = lexeme[1..lexeme.len()-1].to_string();
-                match unescape(&string) {
-                    Some(unescaped) => {
-                        self.advance();
-                        Ok(Expr::Literal(LiteralValue::String(unescaped)))
-                    },
-                    None => {
-                        report(&RloxError::LexicalError(
-                            self.peek().line,
-                            "Invalid string escape sequence".to_string(),
-                            string.clone(),
-                        ));
-                        let lit = string.clone();
-                        self.advance();
-                        self.had_error = true;
-                        Ok(Expr::Literal(LiteralValue::String(lit)))
+                let lexeme = self.advance().lexeme.clone();
+                let string = self.string_text(&lexeme[1..lexeme.len()-1]);
+                Ok(Expr::Literal(LiteralValue::String(string.into())))
+            },
+            TokenType::StringPart => {
+                // parts alternate with the interpolated expressions until a part ends the string
+                let mut parts = Vec::new();
+                loop {
+                    let token = self.advance().clone();
+                    let delimiters = if token.t_type == TokenType::StringPart { 2 } else { 1 };
+                    let text = self.string_text(&token.lexeme[1..token.lexeme.len()-delimiters]);
+                    if !text.is_empty() {
+                        parts.push(Expr::Literal(LiteralValue::String(text.into())));
+                    }
+                    if token.t_type == TokenType::String {
+                        break;
+                    }
+                    parts.push(self.expression()?);
+                    if !(self.check(TokenType::StringPart) || self.check(TokenType::String)) || !self.peek().lexeme.starts_with('}') {
+                        return Err(self.error("Expect '}' after interpolated expression"));
                     }
                 }
+                Ok(Expr::Interpolation(parts))
             },
             TokenType::LeftParen => {
                 self.advance();
@@ -319,14 +380,15 @@ impl Parser {
                 Ok(Expr::Grouping(Box::new(expr)))
             },
             TokenType::This => {
-                Ok(Expr::This(self.advance().clone()))
+                let keyword = self.advance().clone();
+                Ok(Expr::This(keyword, self.node_id()))
             },
             TokenType::Super => {
                 self.advance();
                 let keyword = self.previous().clone();
                 self.consume(TokenType::Dot, "Expect '.' after 'super'")?;
                 let method = self.consume(TokenType::Identifier, "Expect superclass method name")?.clone();
-                Ok(Expr::Super(keyword, method))
+                Ok(Expr::Super(keyword, method, self.node_id()))
             },
             _ => Err(self.error("Expected expression")),
         }
@@ -369,6 +431,8 @@ impl Parser {
             self.function_declaration("function")
         } else if self.match_token(vec![TokenType::Class]) {
             self.class_declaration()
+        } else if self.at_test_declaration() {
+            self.test_declaration()
         } else {
             self.statement()
         } {
@@ -380,15 +444,43 @@ impl Parser {
         }
     }
 
+    /// `test` is only a keyword when a test name follows it,
+    /// so it can still be used as an ordinary identifier.
+    fn at_test_declaration(&self) -> bool {
+        self.check(TokenType::Identifier) && self.peek().lexeme == "test"
+            && self.tokens.get(self.current + 1).is_some_and(|next| next.t_type == TokenType::String)
+    }
+
+    fn test_declaration(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.advance().clone();
+        let lexeme = self.advance().lexeme.clone();
+        let name = self.string_text(&lexeme[1..lexeme.len() - 1]);
+        self.consume(TokenType::LeftBrace, "Expect '{' before test body")?;
+        let body = match self.block()? {
+            Stmt::Block(block) => block,
+            _ => panic!("should not happen"),
+        };
+        Ok(Stmt::Test(keyword, name, Rc::new(body)))
+    }
+
     fn var_declaration(&mut self) -> Result<Stmt, RloxError> {
         let name = self.consume(TokenType::Identifier, "Expect variable name")?.clone();
+        let annotation = self.type_annotation()?;
         let mut initializer: Option<Expr> = None;
         if self.match_token(vec![TokenType::Equal]) {
             let expression = self.expression()?;
             initializer = Some(expression);
         }
         self.consume(TokenType::Semicolon, "Expect ';' after variable declaration")?;
-        Ok(Stmt::Var(name, initializer))
+        Ok(Stmt::Var(name, initializer, annotation))
+    }
+
+    /// An optional `: Type` after a name or parameter list.
+    fn type_annotation(&mut self) -> Result<Option<Token>, RloxError> {
+        if !self.match_token(vec![TokenType::Colon]) {
+            return Ok(None);
+        }
+        Ok(Some(self.consume(TokenType::Identifier, "Expect type name after ':'")?.clone()))
     }
 
     fn statement(&mut self) -> Result<Stmt, RloxError> {
@@ -410,18 +502,21 @@ impl Parser {
     }
 
     fn print_statement(&mut self) -> Result<Stmt, RloxError> {
+        let line = self.previous().line;
         let value = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value")?;
-        Ok(Stmt::Print(value))
+        Ok(Stmt::Print(value, line))
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, RloxError> {
+        let line = self.peek().line;
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after expression")?;
-        Ok(Stmt::Expression(expr))
+        Ok(Stmt::Expression(expr, line))
     }
 
     fn if_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after if condition")?;
@@ -431,18 +526,20 @@ impl Parser {
         } else {
             None
         };
-        Ok(Stmt::If(condition, Box::new(then_branch), else_branch))
+        Ok(Stmt::If(condition, Box::new(then_branch), else_branch, keyword))
     }
 
     fn while_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
         self.consume(TokenType::LeftParen, "Expect '(' after 'while'")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after while condition")?;
         let body = self.statement()?;
-        Ok(Stmt::While(condition, Box::new(body)))
+        Ok(Stmt::While(condition, Box::new(body), keyword))
     }
 
     fn for_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'")?;
         let initializer = if self.match_token(vec![TokenType::Var]) {
             Some(self.var_declaration()?)
@@ -459,6 +556,7 @@ impl Parser {
         self.consume(TokenType::Semicolon, "Expect ';' after loop condition")?;
 
         let mut increment: Option<Expr> = None;
+        let increment_line = self.peek().line;
         if !self.check(TokenType::RightParen) {
             increment = Some(self.expression()?);
         }
@@ -469,9 +567,9 @@ impl Parser {
         // insert increment statement at the end of the body
         if let Some(increment) = increment {
             if let Stmt::Block(ref mut block) = body {
-                block.push(Stmt::Expression(increment));
+                block.push(Stmt::Expression(increment, increment_line));
             } else {
-                body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
+                body = Stmt::Block(vec![body, Stmt::Expression(increment, increment_line)]);
             }
         }
 
@@ -479,6 +577,7 @@ impl Parser {
         let while_body = Stmt::While(
             condition.unwrap_or(Expr::Literal(LiteralValue::Boolean(true))), // if no condition, loop forever
             Box::new(body),
+            keyword,
         );
 
         // then build the outer block
@@ -491,48 +590,56 @@ impl Parser {
     }
 
     fn function_declaration(&mut self, kind: &str) -> Result<Stmt, RloxError> {
+        // a function's doc comment is before `fun`, a method's before its name
+        let start = if self.previous().t_type == TokenType::Fun { self.current - 1 } else { self.current };
+        let doc = self.docs.remove(&start);
         let name = self.consume(TokenType::Identifier, &format!("Expect {} name", kind))?.clone();
         self.consume(TokenType::LeftParen, &format!("Expect '(' after {} name", kind))?;
         let mut params = vec![];
+        let mut signature = Signature::default();
         if !self.check(TokenType::RightParen) {
             loop {
                 if params.len() >= 255 {
                     return Err(self.error("Can't have more than 255 parameters"));
                 }
                 params.push(self.consume(TokenType::Identifier, "Expect parameter name")?.clone());
+                signature.params.push(self.type_annotation()?);
                 if !self.match_token(vec![TokenType::Comma]) {
                     break;
                 }
             }
         }
         self.consume(TokenType::RightParen, "Expect ')' after parameters")?;
+        signature.returns = self.type_annotation()?;
         self.consume(TokenType::LeftBrace, &format!("Expect '{{' before {} body", kind))?;
         let body_block = self.block()?;
         let body = match body_block {
             Stmt::Block(block) => block,
             _ => panic!("should not happen"),
         };
-        Ok(Stmt::FunctionDecl(name, params, Rc::new(body)))
+        Ok(Stmt::FunctionDecl(name, params, Rc::new(body), doc, signature))
     }
 
     fn return_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
         let value = if !self.check(TokenType::Semicolon) {
             Some(self.expression()?)
         } else {
             None
         };
         self.consume(TokenType::Semicolon, "Expect ';' after return value")?;
-        Ok(Stmt::Return(value))
+        Ok(Stmt::Return(keyword, value))
     }
 
     fn class_declaration(&mut self) -> Result<Stmt, RloxError> {
+        let doc = self.docs.remove(&(self.current - 1));
         let name = self.consume(TokenType::Identifier, "Expect class name")?.clone();
         
         let mut super_class: Option<Expr> = None;
         if self.match_token(vec![TokenType::Less]) {
             self.consume(TokenType::Identifier, "Expect superclass name")?;
             let super_name = self.previous().clone();
-            super_class = Some(Expr::Variable(super_name));
+            super_class = Some(Expr::Variable(super_name, self.node_id()));
         }
 
         self.consume(TokenType::LeftBrace, "Expect '{' after class name")?;
@@ -546,6 +653,85 @@ impl Parser {
 
         self.consume(TokenType::RightBrace, "Expect '}' after class body")?;
 
-        Ok(Stmt::ClassDecl(name, super_class, methods))
+        Ok(Stmt::ClassDecl(name, super_class, methods, doc))
+    }
+}
+
+/// Resolves the escape sequences of a string literal. On failure, returns the
+/// offending sequence.
+///
+/// Supported are `\n`, `\t`, `\r`, `\b`, `\f`, `\'`, `\"`, `\\`,
+/// `\$` (which keeps `${` from starting an interpolation), `\u{...}` with
+/// one to six hex digits naming a Unicode scalar value, `\uXXXX` with exactly
+/// four, `\xNN` with two, and octal escapes of up to three digits (`\101`).
+/// A `\0` that is not followed by another octal digit is the NUL character.
+fn unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('b') => '\u{0008}',
+            Some('f') => '\u{000C}',
+            Some(c @ ('\'' | '"' | '\\' | '$')) => c,
+            Some('u') if chars.as_str().starts_with('{') => {
+                let rest = chars.as_str();
+                let code = rest[1..].split_once('}')
+                    .map(|(digits, _)| digits)
+                    .filter(|digits| (1..=6).contains(&digits.len()));
+                let Some(digits) = code else {
+                    return Err(format!("\\u{}", rest.chars().take(8).collect::<String>()));
+                };
+                match code_point(digits, 16) {
+                    Some(c) => {
+                        chars.nth(digits.len() + 1);
+                        c
+                    },
+                    None => return Err(format!("\\u{{{}}}", digits)),
+                }
+            },
+            Some(kind @ ('u' | 'x')) => {
+                let length = if kind == 'u' { 4 } else { 2 };
+                let digits: String = chars.clone().take(length).collect();
+                match code_point(&digits, 16).filter(|_| digits.len() == length) {
+                    Some(c) => {
+                        chars.nth(length - 1);
+                        c
+                    },
+                    None => return Err(format!("\\{}{}", kind, digits)),
+                }
+            },
+            Some(first @ '0'..='7') => {
+                // three digits only when the value fits in a byte, as in C
+                let most = if first <= '3' { 2 } else { 1 };
+                let more = chars.as_str().chars().take(most).take_while(|c| c.is_digit(8)).count();
+                if more == 0 && first != '0' {
+                    return Err(format!("\\{}", first));
+                }
+                let digits = format!("{}{}", first, &chars.as_str()[..more]);
+                for _ in 0..more {
+                    chars.next();
+                }
+                code_point(&digits, 8).unwrap_or_default()
+            },
+            Some(c) => return Err(format!("\\{}", c)),
+            None => return Err("\\".to_string()),
+        };
+        unescaped.push(escaped);
+    }
+    Ok(unescaped)
+}
+
+/// The character whose code is written with `digits` in the given radix.
+fn code_point(digits: &str, radix: u32) -> Option<char> {
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
     }
-}
\ No newline at end of file
+    u32::from_str_radix(digits, radix).ok().and_then(char::from_u32)
+}
@@ -0,0 +1,128 @@
+//! Profiler:
+//! An instrumenting profiler built on the interpreter's call hook. It records
+//! how often each function is called and how much time is spent in it, both
+//! including (inclusive) and excluding (exclusive) the functions it calls.
+//!
+//! Exclusive time is also attributed to the whole call stack, which can be
+//! written in the collapsed format read by flamegraph tools.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::hook::Hook;
+use crate::interpreter::Interpreter;
+
+const SCRIPT: &str = "<script>";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionStats {
+    pub calls: u64,
+    pub inclusive: Duration,
+    pub exclusive: Duration,
+}
+
+/// A call in progress.
+#[derive(Debug)]
+struct Activation {
+    name: String,
+    started: Instant,
+    in_callees: Duration,   // time spent in calls made from this one
+}
+
+#[derive(Debug)]
+pub struct Profiler {
+    pub functions: HashMap<String, FunctionStats>,
+    /// Exclusive time by call stack, with frames joined by `;` from the outermost.
+    pub stacks: HashMap<String, Duration>,
+    stack: Vec<Activation>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    /// Creates a profiler whose clock for the top-level script starts now.
+    pub fn new() -> Self {
+        Profiler {
+            functions: HashMap::new(),
+            stacks: HashMap::new(),
+            stack: vec![Activation { name: SCRIPT.to_string(), started: Instant::now(), in_callees: Duration::ZERO }],
+        }
+    }
+
+    fn stack_key(&self) -> String {
+        self.stack.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join(";")
+    }
+
+    /// Pops the innermost activation and charges its time.
+    fn leave(&mut self) {
+        let key = self.stack_key();
+        let Some(activation) = self.stack.pop() else {
+            return;
+        };
+        let elapsed = activation.started.elapsed();
+        let exclusive = elapsed.saturating_sub(activation.in_callees);
+        // a recursive call is already covered by the inclusive time of its outermost activation
+        let recursive = self.stack.iter().any(|a| a.name == activation.name);
+
+        let stats = self.functions.entry(activation.name).or_default();
+        if !recursive {
+            stats.inclusive += elapsed;
+        }
+        stats.exclusive += exclusive;
+        *self.stacks.entry(key).or_default() += exclusive;
+
+        if let Some(caller) = self.stack.last_mut() {
+            caller.in_callees += elapsed;
+        }
+    }
+
+    /// Stops the clock of calls that are still active, including the top-level script.
+    /// This is called once the program has finished or was aborted by an error.
+    pub fn finish(&mut self) {
+        while !self.stack.is_empty() {
+            self.leave();
+        }
+    }
+
+    /// Writes a table of all functions, the most expensive ones first.
+    pub fn write_summary<W: Write>(&self, mut output: W) -> io::Result<()> {
+        let mut functions: Vec<(&String, &FunctionStats)> = self.functions.iter()
+            .filter(|(name, _)| name.as_str() != SCRIPT)
+            .collect();
+        functions.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then_with(|| a.0.cmp(b.0)));
+
+        let total = self.functions.get(SCRIPT).map_or(Duration::ZERO, |script| script.inclusive);
+        writeln!(output, "{:<24} {:>10} {:>14} {:>14}", "function", "calls", "inclusive ms", "exclusive ms")?;
+        for (name, stats) in functions {
+            writeln!(output, "{:<24} {:>10} {:>14.3} {:>14.3}", name, stats.calls,
+                stats.inclusive.as_secs_f64() * 1000.0, stats.exclusive.as_secs_f64() * 1000.0)?;
+        }
+        writeln!(output, "total: {:.3} ms", total.as_secs_f64() * 1000.0)
+    }
+
+    /// Writes one line per call stack with its exclusive time in microseconds.
+    pub fn write_folded<W: Write>(&self, mut output: W) -> io::Result<()> {
+        let mut stacks: Vec<(&String, &Duration)> = self.stacks.iter().collect();
+        stacks.sort();
+        for (stack, time) in stacks {
+            writeln!(output, "{} {}", stack, time.as_micros())?;
+        }
+        Ok(())
+    }
+}
+
+impl Hook for Profiler {
+    fn on_call_enter(&mut self, _interpreter: &Interpreter, name: &str) {
+        self.functions.entry(name.to_string()).or_default().calls += 1;
+        self.stack.push(Activation { name: name.to_string(), started: Instant::now(), in_callees: Duration::ZERO });
+    }
+
+    fn on_call_exit(&mut self, _interpreter: &Interpreter, _name: &str) {
+        self.leave();
+    }
+}
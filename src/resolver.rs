@@ -1,9 +1,13 @@
 //! Semantic Analysis
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use crate::env::Binding;
 use crate::error::RloxError;
 use crate::interpreter::Interpreter;
+use crate::lint::{Allows, Lint, Warning};
+use crate::value::{LoxFunction, LoxValue};
+use crate::symbols::{SymbolIndex, SymbolKind};
 use crate::ast::*;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -21,12 +25,54 @@ enum ClassType {
     SubClass,
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum LocalKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    Hidden,     // `this` and `super`
+}
+
+/// What the resolver knows about a name declared in a scope.
+#[derive(Debug, Clone)]
+struct Local {
+    defined: bool,
+    used: bool,
+    kind: LocalKind,
+    line: usize,
+    /// The number of arguments a call needs, for functions and for classes with an `init`.
+    arity: Option<usize>,
+    /// Where the value lives at runtime, counting the declarations before it in its scope.
+    slot: usize,
+}
+
 pub struct Resolver<'a> {
     pub interpreter: &'a mut Interpreter,
-    scope: Vec<HashMap<String, bool>>,
+    scope: Vec<HashMap<String, Local>>,
     current_function: FunctionType,
     current_class: ClassType,
     pub had_error: bool,
+    /// Errors met while resolving, kept for tools that report them on their own.
+    pub errors: Vec<RloxError>,
+    /// If set, errors are only collected into `errors` and not printed.
+    pub silent: bool,
+    /// Declarations and references seen so far, used by editor tooling.
+    pub symbols: SymbolIndex,
+    /// Lint warnings, which do not stop the program from running.
+    pub warnings: Vec<Warning>,
+    /// Lints disabled by `// lox-allow:` comments in the source.
+    pub allows: Allows,
+    /// Line of the statement being resolved, for warnings about nodes without a line.
+    line: usize,
+    /// Makes references to globals that are never declared compile errors.
+    pub strict: bool,
+    /// Names declared at the top level of the program, wherever they are.
+    globals: HashSet<String>,
+    /// The arity of the functions and classes declared at the top level.
+    global_arities: HashMap<String, Option<usize>>,
+    /// Names assigned to anywhere in the program, whose calls are not checked.
+    reassigned: HashSet<String>,
 }
 
 impl<'a> Resolver<'a> {
@@ -37,6 +83,16 @@ impl<'a> Resolver<'a> {
             current_function: FunctionType::None,
             current_class: ClassType::None,
             had_error: false,
+            errors: Vec::new(),
+            silent: false,
+            symbols: SymbolIndex::new(),
+            warnings: Vec::new(),
+            allows: Allows::default(),
+            line: 0,
+            strict: false,
+            globals: HashSet::new(),
+            global_arities: HashMap::new(),
+            reassigned: HashSet::new(),
         }
     }
 }
@@ -44,24 +100,51 @@ impl<'a> Resolver<'a> {
 impl<'a> Resolver<'a> {
     fn error(&mut self, err: RloxError) {
         // Handle the error (e.g., log it, print it, etc.)
-        println!("{}", err);
+        if !self.silent {
+            println!("{}", err);
+        }
+        self.errors.push(err);
         self.had_error = true;
     }
 
+    fn warn(&mut self, lint: Lint, line: usize, message: String) {
+        if self.allows.allows(lint, line) {
+            return;
+        }
+        let warning = Warning { lint, line, message };
+        if !self.silent {
+            eprintln!("{}", warning);
+        }
+        self.warnings.push(warning);
+    }
+
     pub fn resolve_program(&mut self, program: &stmt::Stmt) {
         if let Err(err) = program.accept(self) {
             self.had_error = true;
             self.error(err);
         }
+        self.symbols.finish();
     }
 
     fn resolve_stmt(&mut self, stmt: &stmt::Stmt) -> Result<(), RloxError> {
+        if let Some(line) = stmt.line() {
+            self.line = line;
+        }
         stmt.accept(self)
     }
 
     fn resolve_stmts(&mut self, stmts: &[stmt::Stmt]) -> Result<(), RloxError>{
+        let mut after_return = false;
         for stmt in stmts {
+            if after_return {
+                after_return = false;
+                let line = stmt.line().unwrap_or(self.line);
+                self.warn(Lint::UnreachableCode, line, "Unreachable code after 'return'.".to_string());
+            }
             self.resolve_stmt(stmt)?;
+            if let stmt::Stmt::Return(..) = stmt {
+                after_return = true;
+            }
         }
         Ok(())
     }
@@ -79,23 +162,74 @@ impl<'a> Resolver<'a> {
 
     fn begin_scope(&mut self) {
         self.scope.push(HashMap::new());
+        self.symbols.begin_scope();
     }
 
     fn end_scope(&mut self) {
+        let scope = self.scope.pop().unwrap_or_default();
+        self.symbols.end_scope();
+        // globals may be used by code that is resolved later, e.g. in the REPL
+        if self.scope.is_empty() {
+            return;
+        }
+        let mut unused: Vec<(&String, &Local)> = scope.iter()
+            .filter(|(name, local)| !local.used && !name.starts_with('_'))
+            .collect();
+        unused.sort_by_key(|(name, local)| (local.line, name.to_string()));
+        for (name, local) in unused {
+            let (lint, what) = match local.kind {
+                LocalKind::Variable => (Lint::UnusedVariable, "Local variable"),
+                LocalKind::Function => (Lint::UnusedVariable, "Local function"),
+                LocalKind::Class => (Lint::UnusedVariable, "Local class"),
+                LocalKind::Parameter => (Lint::UnusedParameter, "Parameter"),
+                LocalKind::Hidden => continue,
+            };
+            self.warn(lint, local.line, format!("{} '{}' is never used.", what, name));
+        }
+    }
+
+    /// The scope holding `super` only exists at runtime, so the symbol index skips it.
+    fn begin_hidden_scope(&mut self, name: &str) {
+        self.scope.push(HashMap::new());
+        self.declare_hidden(name);
+    }
+
+    /// Declares `this` or `super`, which the source never declares itself.
+    fn declare_hidden(&mut self, name: &str) {
+        if let Some(scope) = self.scope.last_mut() {
+            let local = Local { defined: true, used: true, kind: LocalKind::Hidden, line: 0, arity: None, slot: scope.len() };
+            scope.insert(name.to_string(), local);
+        }
+    }
+
+    fn end_hidden_scope(&mut self) {
         self.scope.pop();
     }
 
-    fn declare(&mut self, name: &token::Token) -> Result<(), RloxError> {
+    fn declare(&mut self, name: &token::Token, kind: LocalKind) -> Result<(), RloxError> {
+        // scope 0 holds the globals, which locals may shadow without a warning
+        let enclosing = self.scope.len().saturating_sub(1);
+        let shadowed = self.scope.iter().take(enclosing).skip(1).rev()
+            .find_map(|scope| scope.get(&name.lexeme))
+            .filter(|local| local.kind != LocalKind::Hidden)
+            .map(|local| local.line);
+        if let Some(line) = shadowed {
+            self.warn(Lint::ShadowedLocal, name.line,
+                format!("Local '{}' shadows the one declared on line {}.", name.lexeme, line));
+        }
+
         let mut error = false;
         if let Some(scope) = self.scope.last_mut() {
             if scope.contains_key(&name.lexeme) {
                 error = true;
             } else {
-                scope.insert(name.lexeme.clone(), false);
+                let slot = scope.len();
+                scope.insert(name.lexeme.clone(), Local { defined: false, used: false, kind, line: name.line, arity: None, slot });
             }
         }
         if error {
             Err(RloxError::SemanticError(
+                name.line,
                 "Already a variable with this name in this scope.".to_string(),
             ))
         } else {
@@ -105,17 +239,88 @@ impl<'a> Resolver<'a> {
 
     fn define(&mut self, name: &token::Token) {
         if let Some(scope) = self.scope.last_mut() {
-            if let Some(defined) = scope.get_mut(&name.lexeme) {
-                *defined = true;
+            if let Some(local) = scope.get_mut(&name.lexeme) {
+                local.defined = true;
             }
         }
     }
 
-    fn resolve_local(&mut self, name: &token::Token) -> Result<(), RloxError> {
+    /// The innermost declaration of a name, if it is not an undeclared global.
+    fn lookup(&mut self, name: &str) -> Option<&mut Local> {
+        self.scope.iter_mut().rev().find_map(|scope| scope.get_mut(name))
+    }
+
+    /// In strict mode, reports a name that is not a local, a global of the program
+    /// or one the interpreter already has, such as a builtin.
+    fn check_declared(&mut self, name: &token::Token) {
+        if !self.strict || self.lookup(&name.lexeme).is_some() || self.globals.contains(&name.lexeme)
+            || self.interpreter.env.global.borrow().table.contains_key(name.lexeme.as_str()) {
+            return;
+        }
+        let mut candidates: Vec<String> = self.scope.iter()
+            .flat_map(|scope| scope.iter())
+            .filter(|(_, local)| local.kind != LocalKind::Hidden)
+            .map(|(name, _)| name.clone())
+            .chain(self.globals.iter().cloned())
+            .chain(self.interpreter.env.global.borrow().table.keys().map(|name| name.to_string()))
+            .collect();
+        candidates.sort();
+        let mut message = format!("Undefined variable '{}'.", name.lexeme);
+        if let Some(suggestion) = suggest(&name.lexeme, &candidates) {
+            message.push_str(&format!(" Did you mean '{}'?", suggestion));
+        }
+        self.error(RloxError::SemanticError(name.line, message));
+    }
+
+    fn set_arity(&mut self, name: &token::Token, arity: Option<usize>) {
+        if let Some(local) = self.scope.last_mut().and_then(|scope| scope.get_mut(&name.lexeme)) {
+            local.arity = arity;
+        }
+    }
+
+    /// The arity of what a name is bound to, if it is a function, a class with an
+    /// `init` or a builtin, and no assignment may have replaced it.
+    fn known_arity(&self, name: &str) -> Option<usize> {
+        if self.reassigned.contains(name) {
+            return None;
+        }
+        // scope 0 only has the globals declared so far, so those come from `global_arities`
+        if let Some(local) = self.scope.iter().skip(1).rev().find_map(|scope| scope.get(name)) {
+            return local.arity;
+        }
+        if let Some(arity) = self.global_arities.get(name) {
+            return *arity;
+        }
+        match self.interpreter.env.global.borrow().table.get(name) {
+            Some(LoxValue::Callable(function @ LoxFunction::BuiltInFunction(..))) => Some(function.arity() as usize),
+            _ => None,
+        }
+    }
+
+    /// The arity of a class: that of its `init`, or else of the `init` it inherits.
+    fn class_arity(&self, super_class: &Option<expr::Expr>, methods: &[stmt::Stmt]) -> Option<usize> {
+        let init = methods.iter().find_map(|method| match method {
+            stmt::Stmt::FunctionDecl(name, params, ..) if name.lexeme == "init" => Some(params.len()),
+            _ => None,
+        });
+        match (init, super_class) {
+            (Some(arity), _) => Some(arity),
+            (None, Some(expr::Expr::Variable(super_name, _))) => self.known_arity(&super_name.lexeme),
+            (None, _) => None,
+        }
+    }
+
+    fn resolve_local(&mut self, name: &token::Token, id: expr::NodeId) -> Result<(), RloxError> {
+        let outermost = self.scope.len().saturating_sub(1);
         for (i, scope) in self.scope.iter().rev().enumerate() {
-            if scope.contains_key(&name.lexeme) {
-                // Variable is defined in this scope
-                self.interpreter.resolve(name, i);
+            if let Some(local) = scope.get(&name.lexeme) {
+                // Variable is defined in this scope, which is the global one if it is the outermost
+                let binding = if i == outermost {
+                    Binding::Global
+                } else {
+                    Binding::Local { depth: i, slot: local.slot }
+                };
+                self.interpreter.resolve(id, binding);
                 return Ok(());
             }
         }
@@ -126,10 +331,15 @@ impl<'a> Resolver<'a> {
         decl: FunctionType) -> Result<(), RloxError> {
         self.begin_scope();
         let old_function = self.current_function.clone();
+        // a method finds the instance it is called on in the first slot of its scope
+        if decl == FunctionType::Method || decl == FunctionType::Initializer {
+            self.declare_hidden("this");
+        }
         self.current_function = decl;
         for param in params {
-            self.declare(param)?;
+            self.declare(param, LocalKind::Parameter)?;
             self.define(param);
+            self.symbols.declare(param, SymbolKind::Parameter);
         }
         self.resolve_stmts(body)?;
         self.end_scope();
@@ -140,6 +350,27 @@ impl<'a> Resolver<'a> {
 
 impl<'a> stmt::Visitor<Result<(), RloxError>> for Resolver<'a> {
     fn visit_program_stmt(&mut self, declarations: &Vec<stmt::Stmt>) -> Result<(), RloxError> {
+        // functions may refer to globals declared further down
+        self.globals = declarations.iter().filter_map(|declaration| match declaration {
+            stmt::Stmt::Var(name, ..) | stmt::Stmt::FunctionDecl(name, ..) | stmt::Stmt::ClassDecl(name, ..) => {
+                Some(name.lexeme.clone())
+            }
+            _ => None,
+        }).collect();
+        self.reassigned.clear();
+        for declaration in declarations.iter() {
+            assigned_names(declaration, &mut self.reassigned);
+        }
+        self.global_arities.clear();
+        for declaration in declarations.iter() {
+            let (name, arity) = match declaration {
+                stmt::Stmt::Var(name, ..) => (name, None),
+                stmt::Stmt::FunctionDecl(name, params, ..) => (name, Some(params.len())),
+                stmt::Stmt::ClassDecl(name, super_class, methods, _) => (name, self.class_arity(super_class, methods)),
+                _ => continue,
+            };
+            self.global_arities.insert(name.lexeme.clone(), arity);
+        }
         self.begin_scope();
         self.current_function = FunctionType::None;
         self.resolve_stmts(declarations)?;
@@ -148,6 +379,9 @@ impl<'a> stmt::Visitor<Result<(), RloxError>> for Resolver<'a> {
     }
 
     fn visit_block_stmt(&mut self, declarations: &Vec<stmt::Stmt>) -> Result<(), RloxError> {
+        if declarations.is_empty() {
+            self.warn(Lint::EmptyBlock, self.line, "Empty block.".to_string());
+        }
         self.begin_scope();
         self.resolve_stmts(declarations)?;
         self.end_scope();
@@ -165,15 +399,24 @@ impl<'a> stmt::Visitor<Result<(), RloxError>> for Resolver<'a> {
     }
 
     fn visit_var_stmt(&mut self, name: &token::Token, initializer: &Option<expr::Expr>) -> Result<(), RloxError> {
-        self.declare(name)?;
+        self.declare(name, LocalKind::Variable)?;
         if let Some(initializer) = initializer {
             self.resolve_expr(initializer)?;
         }
         self.define(name);
+        self.symbols.declare(name, SymbolKind::Variable);
         Ok(())
     }
 
-    fn visit_if_stmt(&mut self, condition: &expr::Expr, then_branch: &Box<stmt::Stmt>, else_branch: &Option<Box<stmt::Stmt>>) -> Result<(), RloxError> {
+    fn visit_if_stmt(&mut self, _keyword: &token::Token, condition: &expr::Expr, then_branch: &Box<stmt::Stmt>, else_branch: &Option<Box<stmt::Stmt>>) -> Result<(), RloxError> {
+        let mut constant = condition;
+        while let expr::Expr::Grouping(inner) = constant {
+            constant = inner;
+        }
+        if let expr::Expr::Literal(value) = constant {
+            let truthy = !matches!(value, expr::LiteralValue::Nil | expr::LiteralValue::Boolean(false));
+            self.warn(Lint::ConstantCondition, self.line, format!("Condition is always {}.", truthy));
+        }
         self.resolve_expr(condition)?;
         self.resolve_stmt(then_branch)?;
         if let Some(else_branch) = else_branch {
@@ -182,22 +425,42 @@ impl<'a> stmt::Visitor<Result<(), RloxError>> for Resolver<'a> {
         Ok(())
     }
 
-    fn visit_while_stmt(&mut self, condition: &expr::Expr, body: &Box<stmt::Stmt>) -> Result<(), RloxError> {
+    fn visit_while_stmt(&mut self, _keyword: &token::Token, condition: &expr::Expr, body: &Box<stmt::Stmt>) -> Result<(), RloxError> {
         self.resolve_expr(condition)?;
         self.resolve_stmt(body)?;
         Ok(())
     }
 
     fn visit_function_decl_stmt(&mut self, name: &token::Token, params: &Vec<token::Token>, body: &Rc<Vec<stmt::Stmt>>) -> Result<(), RloxError> {
-        self.declare(name)?;
+        self.declare(name, LocalKind::Function)?;
         self.define(name);
+        self.set_arity(name, Some(params.len()));
+        if let Some(id) = self.symbols.declare(name, SymbolKind::Function) {
+            self.symbols.symbols[id].params = params.iter().map(|p| p.lexeme.clone()).collect();
+        }
         self.resolve_function(params, body, FunctionType::Function)?;
         Ok(())
     }
 
-    fn visit_return_stmt(&mut self, value: &Option<expr::Expr>) -> Result<(), RloxError> {
+    fn visit_test_stmt(&mut self, keyword: &token::Token, _name: &str, body: &Rc<Vec<stmt::Stmt>>) -> Result<(), RloxError> {
+        // the program itself is the outermost scope
+        if self.scope.len() > 1 {
+            return Err(RloxError::SemanticError(
+                keyword.line,
+                "Tests must be declared at top level.".to_string()
+            ));
+        }
+        // a test body runs in a scope of its own, like a function without parameters
+        self.begin_scope();
+        self.resolve_stmts(body)?;
+        self.end_scope();
+        Ok(())
+    }
+
+    fn visit_return_stmt(&mut self, keyword: &token::Token, value: &Option<expr::Expr>) -> Result<(), RloxError> {
         if self.current_function == FunctionType::None {
             return Err(RloxError::SemanticError(
+                keyword.line,
                 format!("Can't return from top-level code.")
             ));
         }
@@ -205,6 +468,7 @@ impl<'a> stmt::Visitor<Result<(), RloxError>> for Resolver<'a> {
         if let Some(value) = value {
             if self.current_function == FunctionType::Initializer {
                 return Err(RloxError::SemanticError(
+                    keyword.line,
                     format!("Can't return a value from an initializer.")
                 ));
             }
@@ -214,34 +478,40 @@ impl<'a> stmt::Visitor<Result<(), RloxError>> for Resolver<'a> {
     }
 
     fn visit_class_decl_stmt(&mut self, name: &token::Token, super_class: &Option<expr::Expr>, methods: &Vec<stmt::Stmt>) -> Result<(), RloxError> {
-        self.declare(name)?;
+        let arity = self.class_arity(super_class, methods);
+        self.declare(name, LocalKind::Class)?;
         self.define(name);
+        self.set_arity(name, arity);
+        let class_symbol = self.symbols.declare(name, SymbolKind::Class);
 
         let enclosing_class = self.current_class.clone();
         self.current_class = ClassType::Class;
 
         if let Some(super_class) = super_class {
-            if let expr::Expr::Variable(ref super_name) = super_class {
+            if let expr::Expr::Variable(ref super_name, _) = super_class {
                 if super_name.lexeme == name.lexeme {
                     return Err(RloxError::SemanticError(
+                        super_name.line,
                         format!("A class can't inherit from itself.")
                     ));
                 }
+                if let Some(id) = class_symbol {
+                    self.symbols.symbols[id].super_class = Some(super_name.lexeme.clone());
+                }
             }
             self.resolve_expr(super_class)?;
 
-            self.begin_scope();
-            self.scope.last_mut().unwrap().insert("super".to_string(), true);
+            self.begin_hidden_scope("super");
             self.current_class = ClassType::SubClass;
         }
 
-        self.begin_scope();
-        self.scope.last_mut().unwrap().insert("this".to_string(), true);
-        
-
         for method in methods {
-            if let stmt::Stmt::FunctionDecl(name, params, body) = method {
-                let is_initializer = name.lexeme == "init";
+            if let stmt::Stmt::FunctionDecl(method_name, params, body, ..) = method {
+                if let Some(id) = self.symbols.declare(method_name, SymbolKind::Method) {
+                    self.symbols.symbols[id].params = params.iter().map(|p| p.lexeme.clone()).collect();
+                    self.symbols.symbols[id].container = Some(name.lexeme.clone());
+                }
+                let is_initializer = method_name.lexeme == "init";
                 let function_type = if is_initializer {
                     FunctionType::Initializer
                 } else {
@@ -254,9 +524,8 @@ impl<'a> stmt::Visitor<Result<(), RloxError>> for Resolver<'a> {
         }
 
         self.current_class = enclosing_class;
-        self.end_scope();
         if let Some(_super_class) = super_class {
-            self.end_scope();
+            self.end_hidden_scope();
         }
 
         Ok(())
@@ -264,7 +533,18 @@ impl<'a> stmt::Visitor<Result<(), RloxError>> for Resolver<'a> {
 }
 
 impl<'a> expr::Visitor<Result<(), RloxError>> for Resolver<'a> {
-    fn visit_binary_expr(&mut self, left: &expr::Expr, _operator: &token::Token, right: &expr::Expr) -> Result<(), RloxError> {
+    fn visit_binary_expr(&mut self, left: &expr::Expr, operator: &token::Token, right: &expr::Expr) -> Result<(), RloxError> {
+        let always = match operator.t_type {
+            token::TokenType::EqualEqual | token::TokenType::LessEqual | token::TokenType::GreaterEqual => Some(true),
+            token::TokenType::BangEqual | token::TokenType::Less | token::TokenType::Greater => Some(false),
+            _ => None,
+        };
+        if let (Some(always), Some(text)) = (always, comparable_text(left)) {
+            if comparable_text(right).as_ref() == Some(&text) {
+                self.warn(Lint::SelfComparison, operator.line,
+                    format!("'{}' is compared with itself, which is always {}.", text, always));
+            }
+        }
         left.accept(self)?;
         right.accept(self)?;
         Ok(())
@@ -290,29 +570,53 @@ impl<'a> expr::Visitor<Result<(), RloxError>> for Resolver<'a> {
         Ok(())
     }
 
-    fn visit_variable_expr(&mut self, name: &token::Token) -> Result<(), RloxError> {
+    fn visit_variable_expr(&mut self, name: &token::Token, id: expr::NodeId) -> Result<(), RloxError> {
         if !self.scope.is_empty() {
             if let Some(scope) = self.scope.last() {
-                if let Some(defined) = scope.get(&name.lexeme) {
-                    if !defined {
+                if let Some(local) = scope.get(&name.lexeme) {
+                    if !local.defined {
                         return Err(RloxError::SemanticError(
+                            name.line,
                             format!("Can't read local variable in its own initializer.")
                         ));
                     }
                 }
             }
         }
-        self.resolve_local(name)?;
+        self.check_declared(name);
+        if let Some(local) = self.lookup(&name.lexeme) {
+            local.used = true;
+        }
+        self.resolve_local(name, id)?;
+        self.symbols.reference(name);
         Ok(())
     }
 
-    fn visit_assign_expr(&mut self, left: &token::Token, right: &expr::Expr) -> Result<(), RloxError> {
+    fn visit_assign_expr(&mut self, left: &token::Token, right: &expr::Expr, id: expr::NodeId) -> Result<(), RloxError> {
+        let declared = match self.lookup(&left.lexeme).map(|local| local.kind) {
+            Some(LocalKind::Function) => Some("function"),
+            Some(LocalKind::Class) => Some("class"),
+            _ => None,
+        };
+        if let Some(what) = declared {
+            self.warn(Lint::AssignToDeclaration, left.line, format!("Assignment to {} '{}'.", what, left.lexeme));
+        }
         right.accept(self)?;
-        self.resolve_local(left)?;
+        self.check_declared(left);
+        self.resolve_local(left, id)?;
+        self.symbols.reference(left);
         Ok(())
     }
 
-    fn visit_call_expr(&mut self, callee: &expr::Expr, arguments: &[expr::Expr]) -> Result<(), RloxError> {
+    fn visit_call_expr(&mut self, callee: &expr::Expr, arguments: &[expr::Expr], _id: expr::NodeId) -> Result<(), RloxError> {
+        if let expr::Expr::Variable(name, _) = callee {
+            if let Some(arity) = self.known_arity(&name.lexeme).filter(|arity| *arity != arguments.len()) {
+                self.error(RloxError::SemanticError(
+                    name.line,
+                    format!("Expected {} arguments but got {}.", arity, arguments.len()),
+                ));
+            }
+        }
         callee.accept(self)?;
         for argument in arguments {
             argument.accept(self)?;
@@ -320,39 +624,146 @@ impl<'a> expr::Visitor<Result<(), RloxError>> for Resolver<'a> {
         Ok(())
     }
 
-    fn visit_get_expr(&mut self, object: &expr::Expr, _name: &token::Token) -> Result<(), RloxError> {
+    fn visit_get_expr(&mut self, object: &expr::Expr, name: &token::Token) -> Result<(), RloxError> {
         object.accept(self)?;
+        self.symbols.property(name);
         Ok(())
     }
 
-    fn visit_set_expr(&mut self, object: &expr::Expr, _name: &token::Token, value: &expr::Expr) -> Result<(), RloxError> {
+    fn visit_set_expr(&mut self, object: &expr::Expr, name: &token::Token, value: &expr::Expr) -> Result<(), RloxError> {
         object.accept(self)?;
         value.accept(self)?;
+        self.symbols.property(name);
         Ok(())
     }
 
-    fn visit_this_expr(&mut self, name: &token::Token) -> Result<(), RloxError> {
+    fn visit_this_expr(&mut self, name: &token::Token, id: expr::NodeId) -> Result<(), RloxError> {
         if self.current_class == ClassType::None {
             return Err(RloxError::SemanticError(
+                name.line,
                 format!("Can't use 'this' outside of a class.")
             ));
         }
-        self.resolve_local(name)?;
+        self.resolve_local(name, id)?;
         Ok(())
     }
 
-    fn visit_super_expr(&mut self, keyword: &token::Token, _method: &token::Token) -> Result<(), RloxError> {
+    fn visit_super_expr(&mut self, keyword: &token::Token, method: &token::Token, id: expr::NodeId) -> Result<(), RloxError> {
         if self.current_class == ClassType::None {
             return Err(RloxError::SemanticError(
+                keyword.line,
                 format!("Can't use 'super' outside of a class.")
             ));
         } else if self.current_class == ClassType::Class {
             return Err(RloxError::SemanticError(
+                keyword.line,
                 format!("Can't use 'super' in a class with no superclass.")
             ));
         }
-        self.resolve_local(keyword)?;
+        self.resolve_local(keyword, id)?;
+        self.symbols.property(method);
         Ok(())
     }
 
-}
\ No newline at end of file
+    fn visit_interpolation_expr(&mut self, parts: &[expr::Expr]) -> Result<(), RloxError> {
+        self.resolve_exprs(parts)
+    }
+
+}
+
+/// The source text of a variable, `this` or a property chain on them,
+/// which evaluate to the same value on both sides of a comparison.
+fn comparable_text(expr: &expr::Expr) -> Option<String> {
+    match expr {
+        expr::Expr::Variable(name, _) | expr::Expr::This(name, _) => Some(name.lexeme.clone()),
+        expr::Expr::Get(object, name) => Some(format!("{}.{}", comparable_text(object)?, name.lexeme)),
+        expr::Expr::Grouping(inner) => comparable_text(inner),
+        _ => None,
+    }
+}
+
+/// Adds the names that are assigned to anywhere in `stmt` to `names`.
+fn assigned_names(stmt: &stmt::Stmt, names: &mut HashSet<String>) {
+    use stmt::Stmt;
+    match stmt {
+        Stmt::Program(stmts) | Stmt::Block(stmts) | Stmt::ClassDecl(_, None, stmts, _) => {
+            stmts.iter().for_each(|stmt| assigned_names(stmt, names));
+        }
+        Stmt::ClassDecl(_, Some(super_class), stmts, _) => {
+            assigned_in_expr(super_class, names);
+            stmts.iter().for_each(|stmt| assigned_names(stmt, names));
+        }
+        Stmt::FunctionDecl(_, _, body, ..) | Stmt::Test(_, _, body) => {
+            body.iter().for_each(|stmt| assigned_names(stmt, names));
+        }
+        Stmt::Var(_, initializer, _) | Stmt::Return(_, initializer) => {
+            initializer.iter().for_each(|expr| assigned_in_expr(expr, names));
+        }
+        Stmt::Expression(expr, _) | Stmt::Print(expr, _) => assigned_in_expr(expr, names),
+        Stmt::If(condition, then_branch, else_branch, _) => {
+            assigned_in_expr(condition, names);
+            assigned_names(then_branch, names);
+            else_branch.iter().for_each(|stmt| assigned_names(stmt, names));
+        }
+        Stmt::While(condition, body, _) => {
+            assigned_in_expr(condition, names);
+            assigned_names(body, names);
+        }
+    }
+}
+
+fn assigned_in_expr(expr: &expr::Expr, names: &mut HashSet<String>) {
+    use expr::Expr;
+    match expr {
+        Expr::Assign(name, value, _) => {
+            names.insert(name.lexeme.clone());
+            assigned_in_expr(value, names);
+        }
+        Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+            assigned_in_expr(left, names);
+            assigned_in_expr(right, names);
+        }
+        Expr::Grouping(expr) | Expr::Unary(_, expr) | Expr::Get(expr, _) => assigned_in_expr(expr, names),
+        Expr::Call(callee, arguments, ..) => {
+            assigned_in_expr(callee, names);
+            arguments.iter().for_each(|argument| assigned_in_expr(argument, names));
+        }
+        Expr::Set(object, _, value) => {
+            assigned_in_expr(object, names);
+            assigned_in_expr(value, names);
+        }
+        Expr::Interpolation(parts) => parts.iter().for_each(|part| assigned_in_expr(part, names)),
+        Expr::Literal(_) | Expr::Variable(..) | Expr::This(..) | Expr::Super(..) => {}
+    }
+}
+
+/// The candidate closest to `name` by edit distance, if it is close enough to be a typo.
+fn suggest<'c>(name: &str, candidates: &'c [String]) -> Option<&'c str> {
+    let limit = (name.chars().count() / 3).min(3);
+    candidates.iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// The edit distance between two strings, counted in chars: insertions, deletions,
+/// substitutions and swaps of adjacent chars each count as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
@@ -1,16 +1,123 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{self, Read, Write};
+use std::rc::Rc;
+use crate::ast::stmt::Stmt;
+use crate::ast::token::TokenType;
+use crate::checker::Checker;
+use crate::coverage::Coverage;
+use crate::debugger::ConsoleDebugger;
+use crate::doc::ModuleDoc;
 use crate::error::RloxError;
+use crate::highlight;
+use crate::lint::{strict_pragma, Allows};
 use crate::scanner::Scanner;
 use crate::interpreter::Interpreter;
 use crate::resolver::Resolver;
+use crate::optimizer::Optimizer;
 use crate::parser::Parser;
+use crate::profiler::Profiler;
 
-pub fn run_file(filename: &str) -> Result<(), RloxError> {
+/// Checks applied before a script runs, chosen on the command line.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Lint warnings keep the script from running, like errors do.
+    pub deny_warnings: bool,
+    /// Undeclared globals are compile errors instead of runtime errors.
+    pub strict: bool,
+    /// Runs the optimizer over the program before it is interpreted.
+    pub optimize: bool,
+}
+
+impl Options {
+    /// Applies `arg` if it is one of the options, and returns whether it was.
+    pub fn parse_flag(&mut self, arg: &str) -> bool {
+        match arg {
+            "--deny-warnings" => self.deny_warnings = true,
+            "--strict" => self.strict = true,
+            "-O" => self.optimize = true,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Runs a script. Returns false if it was not run because `--deny-warnings`
+/// denied its warnings. Other compile errors are only reported.
+pub fn run_file(filename: &str, options: &Options) -> Result<bool, RloxError> {
+    let mut file = File::open(filename)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(run_tree_walk(contents, options))
+}
+
+/// Runs a script under the interactive debugger, which reads commands from stdin.
+pub fn debug_file(filename: &str, options: &Options) -> Result<bool, RloxError> {
+    let mut file = File::open(filename)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let mut interpreter = Interpreter::new();
+    let program = match compile(contents.clone(), &mut interpreter, options) {
+        Ok(program) => program,
+        Err(rejected) => return Ok(rejected != Rejected::DeniedWarnings),
+    };
+    let debugger = ConsoleDebugger::new(&contents, io::stdin().lock(), io::stdout());
+    interpreter.hook = Some(Box::new(debugger));
+    interpreter.interpret(program);
+    Ok(true)
+}
+
+/// Runs a script under the profiler. A summary is printed to stderr and the
+/// collapsed call stacks are written to `profile`.
+pub fn profile_file(filename: &str, profile: &str, options: &Options) -> Result<bool, RloxError> {
     let mut file = File::open(filename)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
-    run_tree_walk(contents);
+    let mut interpreter = Interpreter::new();
+    let program = match compile(contents, &mut interpreter, options) {
+        Ok(program) => program,
+        Err(rejected) => return Ok(rejected != Rejected::DeniedWarnings),
+    };
+    let profiler = Rc::new(RefCell::new(Profiler::new()));
+    interpreter.hook = Some(Box::new(Rc::clone(&profiler)));
+    interpreter.interpret(program);
+    interpreter.hook = None;
+
+    let mut profiler = profiler.borrow_mut();
+    profiler.finish();
+    profiler.write_summary(io::stderr())?;
+    profiler.write_folded(File::create(profile)?)?;
+    Ok(true)
+}
+
+/// Runs a script while recording coverage, and writes an LCOV report to `report`.
+pub fn coverage_file(filename: &str, report: &str, options: &Options) -> Result<bool, RloxError> {
+    let mut file = File::open(filename)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let mut interpreter = Interpreter::new();
+    let program = match compile(contents, &mut interpreter, options) {
+        Ok(program) => program,
+        Err(rejected) => return Ok(rejected != Rejected::DeniedWarnings),
+    };
+    let coverage = Rc::new(RefCell::new(Coverage::new(&program)));
+    interpreter.hook = Some(Box::new(Rc::clone(&coverage)));
+    interpreter.interpret(program);
+    interpreter.hook = None;
+
+    let coverage = coverage.borrow();
+    coverage.write_lcov(filename, File::create(report)?)?;
+    eprintln!("{}", coverage.summary());
+    Ok(true)
+}
+
+/// Prints a script with syntax highlighting.
+pub fn highlight_file(filename: &str, format: highlight::Format) -> Result<(), RloxError> {
+    let mut file = File::open(filename)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    print!("{}", highlight::highlight(&contents, format));
     Ok(())
 }
 
@@ -20,58 +127,170 @@ pub fn run_prompt() -> Result<(), RloxError> {
     let mut buffer = String::new();
     let mut interpreter = Interpreter::new();
     let mut resolver = Resolver::new(&mut interpreter);
+    let mut docs = BTreeMap::new();
 
     loop {
         print!("> ");
         stdout.flush()?;
         buffer.clear();
-        stdin.read_line(&mut buffer)?;
-        if buffer.trim().is_empty() {
-            continue;
+        if stdin.read_line(&mut buffer)? == 0 {
+            return Ok(());
+        }
+        while is_incomplete(&buffer) {
+            print!(". ");
+            stdout.flush()?;
+            if stdin.read_line(&mut buffer)? == 0 {
+                break;
+            }
+        }
+        match buffer.trim() {
+            "" => continue,
+            "help" => print_help(&docs),
+            command if command.starts_with("help ") => {
+                let name = command["help ".len()..].trim();
+                match docs.get(name) {
+                    Some(text) => println!("{}", text),
+                    None => println!("No documentation for '{}'.", name),
+                }
+            }
+            _ => {
+                if let Some(program) = run_tree_walk_continuous(buffer.clone(), &mut resolver) {
+                    collect_docs(&program, &mut docs);
+                }
+            }
         }
-        run_tree_walk_continuous(buffer.clone(), &mut resolver);
     }
 }
 
-fn run_tree_walk(source: String) {
+/// Whether a REPL entry needs more lines: it has unclosed braces or block
+/// comments, or ends with a doc comment whose declaration is still to come.
+fn is_incomplete(source: &str) -> bool {
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.silent = true;
+    let tokens = scanner.scan_tokens();
+    let mut depth = 0;
+    for token in &tokens {
+        match token.t_type {
+            TokenType::LeftBrace => depth += 1,
+            TokenType::RightBrace => depth -= 1,
+            _ => {}
+        }
+    }
+    let ends_with_doc = tokens.iter().rev().nth(1).is_some_and(|token| token.t_type == TokenType::DocComment);
+    let open_comment = scanner.errors.iter().any(|error| error.message().starts_with("Unterminated block comment"));
+    depth > 0 || ends_with_doc || open_comment
+}
+
+fn print_help(docs: &BTreeMap<String, String>) {
+    println!("Enter Lox statements to run them. Commands:");
+    println!("  help           show this message");
+    println!("  help <name>    show the documentation of a function, class or Class.method");
+    if !docs.is_empty() {
+        let names: Vec<&str> = docs.keys().map(String::as_str).collect();
+        println!("Declared: {}", names.join(", "));
+    }
+}
+
+/// Remembers the signatures and doc comments of the top-level declarations in
+/// `program` for the `help` command.
+fn collect_docs(program: &Stmt, docs: &mut BTreeMap<String, String>) {
+    let help = |signature: String, doc: &Option<String>| match doc {
+        Some(doc) => format!("{}\n{}", signature, doc),
+        None => signature,
+    };
+    let module = ModuleDoc::from_program(program);
+    for function in module.functions {
+        docs.insert(function.name.clone(), help(format!("fun {}", function.signature()), &function.doc));
+    }
+    for class in module.classes {
+        docs.insert(class.name.clone(), help(class.header(), &class.doc));
+        for method in class.methods {
+            let signature = format!("{}.{}", class.name, method.signature());
+            docs.insert(format!("{}.{}", class.name, method.name), help(signature, &method.doc));
+        }
+    }
+}
+
+fn run_tree_walk(source: String, options: &Options) -> bool {
+    let mut interpreter = Interpreter::new();
+    let program = match compile(source, &mut interpreter, options) {
+        Ok(program) => program,
+        Err(rejected) => return rejected != Rejected::DeniedWarnings,
+    };
+    interpreter.interpret(program);
+    true
+}
+
+/// Why `compile` gave no program.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Rejected {
+    Errors,
+    DeniedWarnings,
+}
+
+/// Scans, parses and resolves a program for the given interpreter.
+/// Errors and warnings are reported as they are found, and no program is
+/// returned if there were errors, or warnings that the options deny.
+pub(crate) fn compile(source: String, interpreter: &mut Interpreter, options: &Options) -> Result<Stmt, Rejected> {
+    let allows = Allows::from_source(&source);
+    let strict = options.strict || strict_pragma(&source);
     let mut scanner = Scanner::new(source);
     let tokens = scanner.scan_tokens();
     if scanner.had_error {
-        return;
+        return Err(Rejected::Errors);
     }
     let mut parser = Parser::new(tokens);
-    match parser.parse() {
-        Some(program) => {
-            if parser.had_error {
-                return;
-            }
-            let mut interpreter = Interpreter::new();
-            let mut resolver = Resolver::new(&mut interpreter);
-            resolver.resolve_program(&program);
-            if resolver.had_error {
-                return;
-            }
-            interpreter.interpret(program);
-        }
-        None => {}
+    parser.next_id = interpreter.next_node_id;
+    let program = parser.parse().ok_or(Rejected::Errors)?;
+    interpreter.next_node_id = parser.next_id;
+    if parser.had_error {
+        return Err(Rejected::Errors);
+    }
+    let mut resolver = Resolver::new(interpreter);
+    resolver.allows = allows;
+    resolver.strict = strict;
+    resolver.resolve_program(&program);
+    if resolver.had_error {
+        return Err(Rejected::Errors);
     }
+    let mut checker = Checker::new();
+    checker.check_program(&program);
+    if checker.had_error {
+        return Err(Rejected::Errors);
+    }
+    if options.deny_warnings && !resolver.warnings.is_empty() {
+        eprintln!("Error: {} warning(s) denied by --deny-warnings.", resolver.warnings.len());
+        return Err(Rejected::DeniedWarnings);
+    }
+    if options.optimize {
+        return Ok(Optimizer::new().optimize(program));
+    }
+    Ok(program)
 }
 
-fn run_tree_walk_continuous(source: String, resolver: &mut Resolver) {
-    let mut scanner = Scanner::new(source);
+/// Runs one REPL entry and returns its program, unless it had errors.
+fn run_tree_walk_continuous(source: String, resolver: &mut Resolver) -> Option<Stmt> {
+    let mut scanner = Scanner::new(source.clone());
     let tokens = scanner.scan_tokens();
     if scanner.had_error {
-        return;
+        return None;
     }
+    // ids go on from the previous entries, whose functions may still be called
     let mut parser = Parser::new(tokens);
-    match parser.parse() {
-        Some(program) => {
-            if parser.had_error {
-                return;
-            }
-            resolver.resolve_program(&program);
-            resolver.interpreter.interpret(program);
-        }
-        None => {}
+    parser.next_id = resolver.interpreter.next_node_id;
+    let program = parser.parse()?;
+    resolver.interpreter.next_node_id = parser.next_id;
+    if parser.had_error {
+        return None;
     }
-}
\ No newline at end of file
+    resolver.allows = Allows::from_source(&source);
+    resolver.warnings.clear();
+    resolver.resolve_program(&program);
+    let mut checker = Checker::new();
+    checker.check_program(&program);
+    if checker.had_error {
+        return None;
+    }
+    resolver.interpreter.interpret(program.clone());
+    Some(program)
+}
@@ -4,41 +4,65 @@
 //! Author: Wenze Jin
 
 use std::collections::HashMap;
+use unicode_xid::UnicodeXID;
 use crate::ast::token::{Token, TokenType};
 use crate::error::{RloxError, report};
 
 pub struct Scanner {
-    source: String,
+    source: Vec<char>,
     tokens: Vec<Token>,
     start: usize,
     current: usize,
     line: usize,
+    line_start: usize,
+    start_column: usize,
     keywords: HashMap<String, TokenType>,
+    /// Open braces inside each `${...}` being scanned, innermost last.
+    interpolations: Vec<usize>,
     pub had_error: bool,
+    /// Errors met while scanning, kept for tools that report them on their own.
+    pub errors: Vec<RloxError>,
+    /// If set, errors are only collected into `errors` and not printed.
+    pub silent: bool,
+    /// If set, whitespace, comments and text with lexical errors are kept as
+    /// tokens too, so that the lexemes reproduce the source exactly.
+    pub keep_trivia: bool,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Scanner {
         let keywords = generate_keywords();
         Scanner {
-            source,
+            source: source.chars().collect(),
             tokens: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_column: 1,
             keywords,
+            interpolations: Vec::new(),
             had_error: false,
+            errors: Vec::new(),
+            silent: false,
+            keep_trivia: false,
         }
     }
 
     pub fn scan_tokens(&mut self) -> Vec<Token> {
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_column = self.start - self.line_start + 1;
             self.scan_token();
         }
+        if !self.interpolations.is_empty() {
+            self.interpolations.clear();
+            self.error(RloxError::LexicalError(self.line, "Unterminated string interpolation".to_string(), "${".to_string()));
+        }
 
         // push EOF token
-        self.tokens.push(Token::new(TokenType::EOF, "".to_string(), self.line));
+        let column = self.current - self.line_start + 1;
+        self.tokens.push(Token::new_at(TokenType::EOF, "".to_string(), self.line, column));
         
         std::mem::take(&mut self.tokens)
     }
@@ -50,54 +74,92 @@ impl Scanner {
     }
 
     fn scan_token(&mut self) {
-        let c: u8 = self.advance();
+        let c: char = self.advance();
         match c {
             // Single-character tokens
-            b'(' => self.add_token(TokenType::LeftParen),
-            b')' => self.add_token(TokenType::RightParen),
-            b'{' => self.add_token(TokenType::LeftBrace),
-            b'}' => self.add_token(TokenType::RightBrace),
-            b',' => self.add_token(TokenType::Comma),
-            b'.' => self.add_token(TokenType::Dot),
-            b'-' => self.add_token(TokenType::Minus),
-            b'+' => self.add_token(TokenType::Plus),
-            b';' => self.add_token(TokenType::Semicolon),
-            b'*' => self.add_token(TokenType::Star),
+            '(' => self.add_token(TokenType::LeftParen),
+            ')' => self.add_token(TokenType::RightParen),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace);
+            },
+            '}' => {
+                // the brace closing an interpolation resumes the string around it
+                match self.interpolations.last_mut() {
+                    Some(0) => {
+                        self.interpolations.pop();
+                        self.string();
+                    },
+                    Some(depth) => {
+                        *depth -= 1;
+                        self.add_token(TokenType::RightBrace);
+                    },
+                    None => self.add_token(TokenType::RightBrace),
+                }
+            },
+            ',' => self.add_token(TokenType::Comma),
+            '.' => self.add_token(TokenType::Dot),
+            '-' => self.add_token(TokenType::Minus),
+            '+' => self.add_token(TokenType::Plus),
+            ';' => self.add_token(TokenType::Semicolon),
+            ':' => self.add_token(TokenType::Colon),
+            '*' => self.add_token(TokenType::Star),
+            '%' => self.add_token(TokenType::Percent),
 
             // One or two character tokens
-            b'!' => {
-                let is_equal = self.match_ch(b'=');
+            '!' => {
+                let is_equal = self.match_ch('=');
                 self.add_token(if is_equal { TokenType::BangEqual } else { TokenType::Bang });
             },
-            b'=' => {
-                let is_equal = self.match_ch(b'=');
+            '=' => {
+                let is_equal = self.match_ch('=');
                 self.add_token(if is_equal { TokenType::EqualEqual } else { TokenType::Equal });
             },
-            b'<' => {
-                let is_equal = self.match_ch(b'=');
+            '<' => {
+                let is_equal = self.match_ch('=');
                 self.add_token(if is_equal { TokenType::LessEqual } else { TokenType::Less });
             },
-            b'>' => {
-                let is_equal = self.match_ch(b'=');
+            '>' => {
+                let is_equal = self.match_ch('=');
                 self.add_token(if is_equal { TokenType::GreaterEqual } else { TokenType::Greater });
             },
 
             // Slash or comment
-            b'/' => {
-                if self.match_ch(b'/') {    // this is a comment
-                    while self.peek() != b'\n' && !self.is_at_end() {
+            '/' => {
+                if self.match_ch('/') {    // this is a comment
+                    // `///` starts a doc comment, but `////` and longer are ordinary ones
+                    let is_doc = self.peek() == '/' && self.peek_next() != '/';
+                    while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    if is_doc {
+                        self.add_token(TokenType::DocComment);
+                    } else {
+                        self.add_trivia(TokenType::Comment);
+                    }
+                } else if self.match_ch('*') {
+                    self.block_comment();
                 } else {
                     self.add_token(TokenType::Slash);
                 }
             },
             // String literals handling
-            b'"' => self.string(),
+            '"' => self.string(),
 
-            // Ignore whitespaces
-            b' ' | b'\r' | b'\t' => (),
-            b'\n' => self.line += 1,
+            // Whitespaces
+            ' ' | '\r' | '\t' | '\n' => {
+                if c == '\n' {
+                    self.new_line();
+                }
+                while matches!(self.peek(), ' ' | '\r' | '\t' | '\n') {
+                    if self.advance() == '\n' {
+                        self.new_line();
+                    }
+                }
+                self.add_trivia(TokenType::Whitespace);
+            },
 
             // Default handling
             _ => {
@@ -106,28 +168,58 @@ impl Scanner {
                 } else if is_alpha(c) {
                     self.identifier();
                 } else {
-                    self.had_error = true;
-                    report(&RloxError::LexicalError(self.line, "Unexpected character".to_string(), (c as char).to_string()))
+                    self.invalid("Unexpected character")
                 }
             },
         };
     }
 
+    /// The text of the token being scanned.
+    fn lexeme(&self) -> String {
+        self.source[self.start..self.current].iter().collect()
+    }
+
     fn add_token(&mut self, t_type: TokenType) {
-        let text = self.source[self.start..self.current].to_string();
-        self.tokens.push(Token::new(t_type, text, self.line));
+        let text = self.lexeme();
+        self.tokens.push(Token::new_at(t_type, text, self.line, self.start_column));
+    }
+
+    /// Adds a token that is only kept if the scanner keeps trivia.
+    fn add_trivia(&mut self, t_type: TokenType) {
+        if self.keep_trivia {
+            self.add_token(t_type);
+        }
     }
 
-    fn advance(&mut self) -> u8 {
+    /// Reports the text of the token being scanned as malformed.
+    fn invalid(&mut self, message: &str) {
+        self.error(RloxError::LexicalError(self.line, message.to_string(), self.lexeme()));
+        self.add_trivia(TokenType::Invalid);
+    }
+
+    fn error(&mut self, error: RloxError) {
+        self.had_error = true;
+        if !self.silent {
+            report(&error);
+        }
+        self.errors.push(error);
+    }
+
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    fn advance(&mut self) -> char {
         self.current += 1;
-        self.source.as_bytes()[self.current - 1]
+        self.source[self.current - 1]
     }
 
-    fn match_ch(&mut self, ch: u8) -> bool {
+    fn match_ch(&mut self, ch: char) -> bool {
         if self.is_at_end() {
             false
         } else {
-            if ch != self.source.as_bytes()[self.current] {
+            if ch != self.source[self.current] {
                 false
             } else {
                 self.current += 1;
@@ -136,42 +228,53 @@ impl Scanner {
         }
     }
 
-    fn peek(&self) -> u8 {
+    fn peek(&self) -> char {
         if self.is_at_end() {
-            b'\0'
+            '\0'
         } else {
-            self.source.as_bytes()[self.current]
+            self.source[self.current]
         }
     }
 
-    fn peek_next(&self) -> u8 {
+    fn peek_next(&self) -> char {
         if self.current + 1 >= self.source.len() {
-            b'\0'
+            '\0'
         } else {
-            self.source.as_bytes()[self.current + 1]
+            self.source[self.current + 1]
         }
     }
 }
 
 impl Scanner {
+    /// Scans a string literal, or the part of one that ends at `${`.
+    /// The lexeme of a part keeps its delimiters: `"text${`, `}text${` or `}text"`.
     fn string(&mut self) {
-        let mut escaped = false;
-
-        while (self.peek() != b'"' || escaped) && !self.is_at_end() {
-            if self.peek() == b'\n' {
-                self.line += 1;
+        while !self.is_at_end() {
+            match self.peek() {
+                '"' => break,
+                // the escaped character is consumed below along with the backslash
+                '\\' => {
+                    self.advance();
+                    if self.is_at_end() {
+                        break;
+                    }
+                },
+                '$' if self.peek_next() == '{' => {
+                    self.advance();
+                    self.advance();
+                    self.interpolations.push(0);
+                    self.add_token(TokenType::StringPart);
+                    return;
+                },
+                _ => {},
             }
-            if self.peek() == b'\\' {
-                escaped = !escaped;
-            } else {
-                escaped = false;
+            if self.advance() == '\n' {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            self.had_error = true;
-            report(&RloxError::LexicalError(self.line, "Unterminated string".to_string(), self.source[self.start..self.current].to_string()));
+            self.invalid("Unterminated string");
             return;
         }
 
@@ -181,22 +284,62 @@ impl Scanner {
         self.add_token(TokenType::String);
     }
 
+    /// Scans a decimal literal such as `1_000`, `3.14` or `6.02e23`, or an
+    /// integer with a `0x`, `0b` or `0o` prefix. Malformed literals are
+    /// consumed whole so that the error shows all of their text.
     fn number(&mut self) {
-        while is_digit(self.peek()) {
-            self.advance();
+        if self.source[self.start] == '0' && matches!(self.peek(), 'x' | 'X' | 'b' | 'B' | 'o' | 'O') {
+            while is_alpha_numeric(self.peek()) {
+                self.advance();
+            }
+        } else {
+            self.digits();
+            if self.peek() == '.' && is_digit(self.peek_next()) {
+                self.advance();
+                self.digits();
+            }
+            if matches!(self.peek(), 'e' | 'E') {
+                self.advance();
+                if matches!(self.peek(), '+' | '-') {
+                    self.advance();
+                }
+                self.digits();
+            }
         }
-        if self.peek() == b'.' && is_digit(self.peek_next()) {
-            self.advance();
-            while is_digit(self.peek()) {
+        match parse_number(&self.lexeme()) {
+            Some(_) => self.add_token(TokenType::Number),
+            None => self.invalid("Invalid number"),
+        }
+    }
+
+    /// Skips a `/* ... */` comment, which may contain nested block comments.
+    fn block_comment(&mut self) {
+        let line = self.line;
+        let mut depth = 1;
+        while !self.is_at_end() {
+            if self.peek() == '/' && self.peek_next() == '*' {
+                self.advance();
+                self.advance();
+                depth += 1;
+            } else if self.peek() == '*' && self.peek_next() == '/' {
                 self.advance();
+                self.advance();
+                depth -= 1;
+                if depth == 0 {
+                    self.add_trivia(TokenType::Comment);
+                    return;
+                }
+            } else if self.advance() == '\n' {
+                self.new_line();
             }
         }
-        match self.source[self.start..self.current].parse::<f64>() {
-            Ok(_) => self.add_token(TokenType::Number),
-            Err(_) => {
-                self.had_error = true;
-                report(&RloxError::LexicalError(self.line, "Invalid number".to_string(), self.source[self.start..self.current].to_string()))
-            },
+        self.error(RloxError::LexicalError(line, "Unterminated block comment".to_string(), "/*".to_string()));
+        self.add_trivia(TokenType::Invalid);
+    }
+
+    fn digits(&mut self) {
+        while is_digit(self.peek()) || self.peek() == '_' {
+            self.advance();
         }
     }
 
@@ -205,7 +348,7 @@ impl Scanner {
             self.advance();
         }
 
-        let text = self.source[self.start..self.current].to_string();
+        let text = self.lexeme();
         let t_type = self.keywords.get(&text).unwrap_or(&TokenType::Identifier).clone();
         self.add_token(t_type);
     }
@@ -213,21 +356,60 @@ impl Scanner {
 
 /* Helper funtions */
 
-fn is_digit(c: u8) -> bool {
-    c >= b'0' && c <= b'9'
+fn is_digit(c: char) -> bool {
+    c.is_ascii_digit()
+}
+
+fn is_alpha(c: char) -> bool {
+    c == '_' || c.is_xid_start()
+}
+
+fn is_alpha_numeric(c: char) -> bool {
+    c.is_xid_continue()
 }
 
-fn is_alpha(c: u8) -> bool {
-    (c >= b'a' && c <= b'z') || (c >= b'A' && c <= b'Z') || c == b'_'
+/// Returns the value of a number literal, or `None` if it is malformed.
+pub fn parse_number(lexeme: &str) -> Option<f64> {
+    let radix = match lexeme.get(..2) {
+        Some("0x" | "0X") => 16,
+        Some("0b" | "0B") => 2,
+        Some("0o" | "0O") => 8,
+        _ => 10,
+    };
+    if radix != 10 {
+        let digits = &lexeme[2..];
+        if !is_separated_digits(digits, radix) {
+            return None;
+        }
+        return u64::from_str_radix(&digits.replace('_', ""), radix).ok().map(|n| n as f64);
+    }
+
+    let (mantissa, exponent) = match lexeme.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent.trim_start_matches(['+', '-']))),
+        None => (lexeme, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let well_formed = is_separated_digits(integer, 10)
+        && fraction.is_none_or(|digits| is_separated_digits(digits, 10))
+        && exponent.is_none_or(|digits| is_separated_digits(digits, 10));
+    if !well_formed {
+        return None;
+    }
+    lexeme.replace('_', "").parse::<f64>().ok()
 }
 
-fn is_alpha_numeric(c: u8) -> bool {
-    is_alpha(c) || is_digit(c)
+/// Digits of the given radix, where each `_` must stand between two digits.
+fn is_separated_digits(digits: &str, radix: u32) -> bool {
+    !digits.is_empty() && !digits.starts_with('_') && !digits.ends_with('_') && !digits.contains("__")
+        && digits.chars().all(|c| c == '_' || c.is_digit(radix))
 }
 
 /* Keywords Map */
 
-fn generate_keywords() -> HashMap<String, TokenType> {
+pub fn generate_keywords() -> HashMap<String, TokenType> {
     let mut keywords: HashMap<String, TokenType> = HashMap::with_capacity(30);
     keywords.insert("and".to_string(), TokenType::And);
     keywords.insert("class".to_string(), TokenType::Class);
@@ -0,0 +1,205 @@
+//! Symbol index:
+//! Records every declaration and every name reference seen by the `Resolver`,
+//! so that editor tooling can answer "where is this defined" and "who uses this"
+//! without running the program.
+
+use std::collections::HashMap;
+use crate::ast::token::Token;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    Method,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub line: usize,
+    pub column: usize,
+    pub scope: usize,
+    pub params: Vec<String>,        // parameters of functions and methods
+    pub container: Option<String>,  // enclosing class of a method
+    pub super_class: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub line: usize,
+    pub column: usize,
+    pub symbol: Option<usize>,  // None for property accesses and unresolved names
+    pub is_property: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub parent: Option<usize>,
+    pub first_line: usize,
+    pub last_line: usize,
+    names: HashMap<String, usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct SymbolIndex {
+    pub symbols: Vec<Symbol>,
+    pub references: Vec<Reference>,
+    pub scopes: Vec<Scope>,
+    stack: Vec<usize>,
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn begin_scope(&mut self) {
+        let parent = self.stack.last().copied();
+        self.scopes.push(Scope {
+            parent,
+            first_line: usize::MAX,
+            last_line: 0,
+            names: HashMap::new(),
+        });
+        self.stack.push(self.scopes.len() - 1);
+    }
+
+    pub fn end_scope(&mut self) {
+        if let Some(scope) = self.stack.pop() {
+            let (first, last) = (self.scopes[scope].first_line, self.scopes[scope].last_line);
+            if let Some(parent) = self.stack.last().copied() {
+                self.observe_in(parent, first);
+                self.observe_in(parent, last);
+            }
+        }
+    }
+
+    /// Records a declaration in the innermost scope and returns its index.
+    /// Methods are recorded too, but they are not visible as plain names.
+    pub fn declare(&mut self, name: &Token, kind: SymbolKind) -> Option<usize> {
+        let scope = *self.stack.last()?;
+        self.observe(name.line);
+        self.symbols.push(Symbol {
+            name: name.lexeme.clone(),
+            kind,
+            line: name.line,
+            column: name.column,
+            scope,
+            params: Vec::new(),
+            container: None,
+            super_class: None,
+        });
+        let id = self.symbols.len() - 1;
+        if kind != SymbolKind::Method {
+            self.scopes[scope].names.insert(name.lexeme.clone(), id);
+        }
+        Some(id)
+    }
+
+    /// Records a use of a variable, resolved through the enclosing scopes.
+    pub fn reference(&mut self, name: &Token) {
+        self.observe(name.line);
+        let symbol = self.stack.iter().rev()
+            .find_map(|scope| self.scopes[*scope].names.get(&name.lexeme).copied());
+        self.references.push(Reference {
+            name: name.lexeme.clone(),
+            line: name.line,
+            column: name.column,
+            symbol,
+            is_property: false,
+        });
+    }
+
+    /// Records a property access (`a.name`), which can only be matched against methods by name.
+    pub fn property(&mut self, name: &Token) {
+        self.observe(name.line);
+        self.references.push(Reference {
+            name: name.lexeme.clone(),
+            line: name.line,
+            column: name.column,
+            symbol: None,
+            is_property: true,
+        });
+    }
+
+    /// Binds names left unresolved to top-level declarations, like globals are looked up at runtime.
+    pub fn finish(&mut self) {
+        let globals: HashMap<String, usize> = self.symbols.iter().enumerate()
+            .filter(|(_, symbol)| self.scopes[symbol.scope].parent.is_none())
+            .map(|(id, symbol)| (symbol.name.clone(), id))
+            .collect();
+        for reference in self.references.iter_mut() {
+            if reference.symbol.is_none() && !reference.is_property {
+                reference.symbol = globals.get(&reference.name).copied();
+            }
+        }
+    }
+
+    fn observe(&mut self, line: usize) {
+        if let Some(scope) = self.stack.last().copied() {
+            self.observe_in(scope, line);
+        }
+    }
+
+    fn observe_in(&mut self, scope: usize, line: usize) {
+        if line == 0 || line == usize::MAX {
+            return;
+        }
+        let scope = &mut self.scopes[scope];
+        scope.first_line = scope.first_line.min(line);
+        scope.last_line = scope.last_line.max(line);
+    }
+}
+
+/// Queries used by editor tooling.
+impl SymbolIndex {
+    /// Finds the symbol declared or referenced at the given position.
+    /// Property accesses resolve to the first method with the same name.
+    pub fn symbol_at(&self, line: usize, column: usize) -> Option<usize> {
+        let covers = |l: usize, c: usize, name: &str| l == line && c <= column && column < c + name.chars().count();
+        if let Some(id) = self.symbols.iter().position(|s| covers(s.line, s.column, &s.name)) {
+            return Some(id);
+        }
+        let reference = self.references.iter().find(|r| covers(r.line, r.column, &r.name))?;
+        if reference.is_property {
+            self.symbols.iter().position(|s| s.kind == SymbolKind::Method && s.name == reference.name)
+        } else {
+            reference.symbol
+        }
+    }
+
+    /// All references to a symbol. Methods also match property accesses with the same name.
+    pub fn references_to(&self, id: usize) -> Vec<&Reference> {
+        let symbol = &self.symbols[id];
+        self.references.iter()
+            .filter(|r| {
+                r.symbol == Some(id)
+                    || (r.is_property && symbol.kind == SymbolKind::Method && r.name == symbol.name)
+            })
+            .collect()
+    }
+
+    /// Symbols whose scope covers `line` and that are declared before it.
+    /// Top-level declarations are always visible, as globals are late bound.
+    pub fn visible_at(&self, line: usize) -> Vec<&Symbol> {
+        self.symbols.iter()
+            .filter(|s| s.kind != SymbolKind::Method)
+            .filter(|s| {
+                let scope = &self.scopes[s.scope];
+                scope.parent.is_none()
+                    || (scope.first_line <= line && line <= scope.last_line && s.line <= line)
+            })
+            .collect()
+    }
+
+    /// Declarations of the outermost scope, plus methods of top-level classes.
+    pub fn top_level(&self) -> Vec<&Symbol> {
+        self.symbols.iter()
+            .filter(|s| self.scopes[s.scope].parent.is_none() || s.kind == SymbolKind::Method)
+            .collect()
+    }
+}
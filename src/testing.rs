@@ -0,0 +1,426 @@
+//! Test runner:
+//! Runs Lox scripts and checks them against expectations written in the
+//! scripts themselves, in the style of the Crafting Interpreters test suite:
+//!
+//! ```lox
+//! print 1 + 2;            // expect: 3
+//! print nil + 1;          // expect runtime error: Operands must be numbers.
+//! var a = ;               // error at ';': Expected expression.
+//! return 1;               // error: Can't return from top-level code.
+//! ```
+//!
+//! Output expectations are matched in order and may sit on any line.
+//! Error expectations must sit on the line the error is reported at;
+//! `// error:` is for errors that are not reported near a token.
+//! Several annotations may follow each other on one line.
+//!
+//! After a script ran without errors, the tests it declared with
+//! `test "name" { ... }` are run one by one, each in a fresh scope.
+//! A failing test is reported with the line it failed at, and the
+//! remaining tests still run.
+
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::ast::stmt::Stmt;
+use crate::checker::Checker;
+use crate::error::RloxError;
+use crate::hook::Hook;
+use crate::interpreter::Interpreter;
+use crate::lint::strict_pragma;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+const ERROR_AT: &str = "// error at '";
+const ERROR: &str = "// error: ";
+
+/// Line and message of a failed test.
+pub type TestFailure = (usize, String);
+
+/// What a script printed and which errors it reported, each with its source line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Outcome {
+    pub output: Vec<(usize, String)>,
+    pub errors: Vec<(usize, String)>,
+    /// Declared tests by name, with the line and message of their failure.
+    pub tests: Vec<(String, Result<(), TestFailure>)>,
+}
+
+impl Outcome {
+    fn output_lines(&self) -> Vec<String> {
+        self.output.iter().map(|(_, text)| text.clone()).collect()
+    }
+
+    fn error_lines(&self) -> Vec<String> {
+        self.errors.iter().map(|(line, text)| format!("[line {}] {}", line, text)).collect()
+    }
+}
+
+/// Describes an error the way annotations do, e.g. `error at 'x': Expected expression.`.
+fn describe(error: &RloxError) -> String {
+    match error {
+        RloxError::RuntimeError(message) => format!("runtime error: {}", message),
+        _ => match error.near() {
+            Some(near) => format!("error at '{}': {}", near, error.message()),
+            None => format!("error: {}", error.message()),
+        },
+    }
+}
+
+/// Splits the annotations off a source line. Returns the code before them and
+/// the annotations in order, each still starting with `// `.
+fn split_annotations(line: &str) -> (&str, Vec<&str>) {
+    let is_annotation = |text: &str| {
+        [EXPECT, EXPECT_RUNTIME_ERROR, ERROR_AT, ERROR].iter().any(|marker| text.starts_with(marker))
+    };
+    let Some(start) = line.match_indices("//").map(|(at, _)| at).find(|&at| is_annotation(&line[at..])) else {
+        return (line, vec![]);
+    };
+    let mut annotations = vec![];
+    let mut rest = &line[start..];
+    loop {
+        let next = rest.match_indices(" //").map(|(at, _)| at).find(|&at| is_annotation(&rest[at + 1..]));
+        match next {
+            Some(at) => {
+                annotations.push(&rest[..at]);
+                rest = &rest[at + 1..];
+            }
+            None => {
+                annotations.push(rest);
+                break;
+            }
+        }
+    }
+    (&line[..start], annotations)
+}
+
+/// Reads the expected outcome from the annotations in a script.
+pub fn parse_expectations(source: &str) -> Outcome {
+    let mut expected = Outcome::default();
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        for annotation in split_annotations(line).1 {
+            if let Some(text) = annotation.strip_prefix(EXPECT) {
+                expected.output.push((line_number, text.to_string()));
+            } else if let Some(message) = annotation.strip_prefix(EXPECT_RUNTIME_ERROR) {
+                expected.errors.push((line_number, format!("runtime error: {}", message)));
+            } else {
+                // both error forms are stored as written, without the leading `// `
+                expected.errors.push((line_number, annotation[3..].to_string()));
+            }
+        }
+    }
+    expected
+}
+
+/// Collects output line by line, remembering which line of the script was running.
+#[derive(Clone, Default)]
+struct Capture {
+    lines: Rc<RefCell<Vec<(usize, String)>>>,
+    partial: Rc<RefCell<String>>,
+    line: Rc<Cell<usize>>,
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut partial = self.partial.borrow_mut();
+        partial.push_str(&String::from_utf8_lossy(buf));
+        while let Some(end) = partial.find('\n') {
+            let text: String = partial.drain(..=end).collect();
+            self.lines.borrow_mut().push((self.line.get(), text.trim_end_matches('\n').to_string()));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Hook for Capture {
+    fn before_stmt(&mut self, interpreter: &mut Interpreter, _stmt: &Stmt, _line: usize) -> Result<(), RloxError> {
+        // output is attributed to the top-level statement that caused it
+        self.line.set(interpreter.frames[0].line);
+        Ok(())
+    }
+}
+
+/// Runs a script in this process and records what it did. Nothing is printed.
+pub fn run_source(source: &str) -> Outcome {
+    let mut outcome = Outcome::default();
+    let mut errors = vec![];
+
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.silent = true;
+    let tokens = scanner.scan_tokens();
+    errors.append(&mut scanner.errors);
+
+    let mut parser = Parser::new(tokens);
+    parser.silent = true;
+    let program = parser.parse();
+    errors.append(&mut parser.errors);
+
+    let mut interpreter = Interpreter::new();
+    if errors.is_empty() {
+        if let Some(program) = &program {
+            let mut resolver = Resolver::new(&mut interpreter);
+            resolver.silent = true;
+            resolver.strict = strict_pragma(source);
+            resolver.resolve_program(program);
+            errors.append(&mut resolver.errors);
+            if errors.is_empty() {
+                let mut checker = Checker::new();
+                checker.silent = true;
+                checker.check_program(program);
+                errors.append(&mut checker.errors);
+            }
+        }
+    }
+    if !errors.is_empty() {
+        outcome.errors = errors.iter().map(|e| (e.line().unwrap_or(0), describe(e))).collect();
+        return outcome;
+    }
+    let Some(program) = program else {
+        return outcome;
+    };
+
+    let capture = Capture::default();
+    interpreter.output = Box::new(capture.clone());
+    interpreter.hook = Some(Box::new(capture.clone()));
+    if let Err(error) = program.accept(&mut interpreter) {
+        let line = interpreter.error_line.unwrap_or(0);
+        outcome.errors.push((line, describe(&error)));
+    } else {
+        for test in std::mem::take(&mut interpreter.tests) {
+            let result = interpreter.run_test(&test)
+                .map_err(|error| (interpreter.error_line.unwrap_or(test.line), error.message()));
+            outcome.tests.push((test.name, result));
+        }
+    }
+    interpreter.hook = None;
+    // a print always ends its line, so there is nothing left in `partial`
+    outcome.output = capture.lines.take();
+    outcome
+}
+
+/// Lines of a line-based diff: ` ` for common lines, `-` for expected and `+` for actual ones.
+pub fn diff(expected: &[String], actual: &[String]) -> Vec<String> {
+    // longest common subsequence, filled from the end
+    let (n, m) = (expected.len(), actual.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = vec![];
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            lines.push(format!("  {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("- {}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", actual[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Compares a script's outcome with its annotations and checks that its tests passed.
+/// Returns the number of tests on success, and a report of what went wrong otherwise.
+pub fn check(source: &str) -> Result<usize, Vec<String>> {
+    let expected = parse_expectations(source);
+    let actual = run_source(source);
+    let mut report = vec![];
+    if expected.output_lines() != actual.output_lines() {
+        report.push("output:".to_string());
+        report.extend(diff(&expected.output_lines(), &actual.output_lines()));
+    }
+    if expected.error_lines() != actual.error_lines() {
+        report.push("errors:".to_string());
+        report.extend(diff(&expected.error_lines(), &actual.error_lines()));
+    }
+    let failures: Vec<String> = actual.tests.iter()
+        .filter_map(|(name, result)| result.as_ref().err().map(|(line, message)| {
+            format!("test \"{}\" failed at line {}: {}", name, line, message)
+        }))
+        .collect();
+    if !failures.is_empty() {
+        report.push("tests:".to_string());
+        report.extend(failures);
+    }
+    if report.is_empty() { Ok(actual.tests.len()) } else { Err(report) }
+}
+
+/// Rewrites the annotations of a script so that they describe `outcome`.
+pub fn bless(source: &str, outcome: &Outcome) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut annotations: Vec<Vec<String>> = vec![vec![]; lines.len() + 1];
+    let last = lines.len().max(1);
+
+    // output must be expected in order, so a line never goes before the previous one
+    let mut previous = 1;
+    for (line, text) in &outcome.output {
+        let line = (*line).clamp(previous, last);
+        annotations[line - 1].push(format!("{}{}", EXPECT, text));
+        previous = line;
+    }
+    for (line, text) in &outcome.errors {
+        let line = (*line).clamp(1, last);
+        annotations[line - 1].push(format!("// {}", text).replacen("// runtime error: ", EXPECT_RUNTIME_ERROR, 1));
+    }
+
+    let mut blessed = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let (code, old) = split_annotations(line);
+        let code = if old.is_empty() { *line } else { code.trim_end() };
+        // a line that held nothing but annotations is dropped if it gets none back
+        if !old.is_empty() && code.trim().is_empty() && annotations[i].is_empty() {
+            continue;
+        }
+        blessed.push_str(code);
+        if !annotations[i].is_empty() {
+            if !code.trim().is_empty() {
+                blessed.push(' ');
+            }
+            blessed.push_str(&annotations[i].join(" "));
+        }
+        blessed.push('\n');
+    }
+    if lines.is_empty() && !annotations[0].is_empty() {
+        blessed.push_str(&annotations[0].join(" "));
+        blessed.push('\n');
+    }
+    blessed
+}
+
+/// Finds all `.lox` files under the given files and directories, sorted by path.
+pub fn discover(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    fn visit(path: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+        if path.is_dir() {
+            for entry in fs::read_dir(path)? {
+                visit(&entry?.path(), found)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            found.push(path.to_path_buf());
+        }
+        Ok(())
+    }
+    let mut found = vec![];
+    for path in paths {
+        if path.is_file() {
+            found.push(path.clone());
+        } else {
+            visit(path, &mut found)?;
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+#[derive(Debug)]
+pub enum TestResult {
+    Pass(usize),    // number of declared tests that passed
+    Fail(Vec<String>),
+    Blessed,
+    Error(String),  // the file could not be read or written
+}
+
+fn run_test(path: &Path, bless_it: bool) -> TestResult {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => return TestResult::Error(error.to_string()),
+    };
+    match check(&source) {
+        Ok(tests) => TestResult::Pass(tests),
+        Err(_) if bless_it => {
+            let blessed = bless(&source, &run_source(&source));
+            if let Err(error) = fs::write(path, &blessed) {
+                return TestResult::Error(error.to_string());
+            }
+            // failing tests can't be blessed away
+            match check(&blessed) {
+                Ok(_) => TestResult::Blessed,
+                Err(report) => TestResult::Fail(report),
+            }
+        }
+        Err(report) => TestResult::Fail(report),
+    }
+}
+
+/// Runs scripts on all available cores. Results are in the order of `paths`.
+pub fn run_tests(paths: &[PathBuf], bless_them: bool) -> Vec<TestResult> {
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get()).min(paths.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<TestResult>>> = Mutex::new((0..paths.len()).map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else {
+                    break;
+                };
+                let result = run_test(path, bless_them);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().map(|result| result.unwrap()).collect()
+}
+
+/// Runs the scripts under `paths` and prints a report. Returns whether all of them passed.
+pub fn run_suite<W: Write>(paths: &[PathBuf], bless_them: bool, mut output: W) -> io::Result<bool> {
+    let files = discover(paths)?;
+    let results = run_tests(&files, bless_them);
+    let (mut passed, mut failed, mut blessed) = (0, 0, 0);
+    for (path, result) in files.iter().zip(&results) {
+        match result {
+            TestResult::Pass(0) => {
+                passed += 1;
+                writeln!(output, "PASS {}", path.display())?;
+            }
+            TestResult::Pass(tests) => {
+                passed += 1;
+                writeln!(output, "PASS {} ({} test{})", path.display(), tests, if *tests == 1 { "" } else { "s" })?;
+            }
+            TestResult::Blessed => {
+                blessed += 1;
+                writeln!(output, "BLESS {}", path.display())?;
+            }
+            TestResult::Fail(report) => {
+                failed += 1;
+                writeln!(output, "FAIL {}", path.display())?;
+                for line in report {
+                    writeln!(output, "    {}", line)?;
+                }
+            }
+            TestResult::Error(error) => {
+                failed += 1;
+                writeln!(output, "FAIL {}: {}", path.display(), error)?;
+            }
+        }
+    }
+    write!(output, "\n{} passed, {} failed", passed, failed)?;
+    if bless_them {
+        write!(output, ", {} blessed", blessed)?;
+    }
+    writeln!(output)?;
+    Ok(failed == 0)
+}
@@ -1,22 +1,23 @@
 //! Implements values in lox language.
 
-use crate::ast::token::{Token, TokenType};
 use crate::error::RloxError;
-use crate::interpreter::Interpreter;
+use crate::interpreter::{CallFrame, Interpreter};
 use crate::ast::stmt::Stmt;
 use crate::class::{LoxClass, LoxInstance};
 use std::cell::RefCell;
 use std::rc::Rc;
 use crate::env::{EnvItem, Environment};
+use crate::intern::Symbol;
 
 #[derive(Debug, Clone)]
 pub enum LoxValue {
     Number(f64),
     Class(Rc<RefCell<LoxClass>>),
-    String(String),
+    String(Rc<str>),
     Boolean(bool),
     Callable(LoxFunction),
     Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<LoxValue>>>),
     Null,
 }
 
@@ -28,22 +29,33 @@ impl PartialEq for LoxValue {
             (LoxValue::Boolean(a), LoxValue::Boolean(b)) => a == b,
             (LoxValue::Null, LoxValue::Null) => true,
             (LoxValue::Instance(a), LoxValue::Instance(b)) => Rc::ptr_eq(&a, &b),
+            (LoxValue::List(a), LoxValue::List(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
 }
 
 
+/// Implementation of a native function.
+pub type NativeFn = fn(Vec<LoxValue>) -> Result<LoxValue, RloxError>;
+
 #[derive(Debug, Clone)]
 pub enum LoxFunction {
-    BuiltInFunction(u32, fn(Vec<LoxValue>) -> Result<LoxValue, RloxError>),     // (Arity, Function)
+    BuiltInFunction(&'static str, u32, NativeFn),     // (Name, Arity, Function)
+    /// A native function called as a method, e.g. `s.len()`. The receiver is
+    /// passed as the first argument and is not counted in the arity.
+    BoundBuiltIn(Box<LoxValue>, &'static str, u32, NativeFn),     // (Receiver, Name, Arity, Function)
     UserFunction {
-        def_name: String,
-        params: Vec<String>,
+        def_name: Symbol,
+        params: Vec<Symbol>,
         body: Rc<Vec<Stmt>>,
         closure: Rc<RefCell<EnvItem>>,  // Environment of this function
         is_initializer: bool,
+        class_name: Option<Symbol>,     // the class a method is declared in
     },
+    /// A method of a class together with the instance it was looked up on, which
+    /// the method sees as `this`.
+    BoundMethod(Rc<RefCell<LoxInstance>>, Rc<LoxFunction>),     // (Receiver, Method)
 }
 
 
@@ -52,12 +64,16 @@ impl ToString for LoxValue {
     fn to_string(&self) -> String {
         match self {
             LoxValue::Number(n) => n.to_string(),
-            LoxValue::String(s) => s.clone(),
+            LoxValue::String(s) => s.to_string(),
             LoxValue::Boolean(b) => b.to_string(),
             LoxValue::Null => "nil".to_string(),
             LoxValue::Callable(f) => f.to_string(),
             LoxValue::Instance(i) => i.borrow().to_string(),
             LoxValue::Class(c) => c.borrow().to_string(),
+            LoxValue::List(items) => {
+                let items: Vec<String> = items.borrow().iter().map(LoxValue::to_string).collect();
+                format!("[{}]", items.join(", "))
+            }
         }
     }
 }
@@ -67,8 +83,9 @@ impl ToString for LoxValue {
 impl ToString for LoxFunction {
     fn to_string(&self) -> String {
         match self {
-            LoxFunction::BuiltInFunction(_, _) => "<native fn>".to_string(),
+            LoxFunction::BuiltInFunction(..) | LoxFunction::BoundBuiltIn(..) => "<native fn>".to_string(),
             LoxFunction::UserFunction{def_name, ..} => format!("<fn {}>", def_name),
+            LoxFunction::BoundMethod(_, method) => method.to_string(),
         }
     }
 }
@@ -77,7 +94,26 @@ impl LoxFunction {
     pub fn arity (&self) -> u32 {
         match self {
             LoxFunction::UserFunction{params, .. } => params.len() as u32,
-            LoxFunction::BuiltInFunction(arity, _) => *arity,
+            LoxFunction::BuiltInFunction(_, arity, _) | LoxFunction::BoundBuiltIn(_, _, arity, _) => *arity,
+            LoxFunction::BoundMethod(_, method) => method.arity(),
+        }
+    }
+
+    /// The name a function was declared with.
+    pub fn name(&self) -> &str {
+        match self {
+            LoxFunction::UserFunction{def_name, .. } => def_name,
+            LoxFunction::BuiltInFunction(name, _, _) | LoxFunction::BoundBuiltIn(_, name, _, _) => name,
+            LoxFunction::BoundMethod(_, method) => method.name(),
+        }
+    }
+
+    /// The name of a function, or `Class.method` for a method, as the profiler reports it.
+    pub fn qualified_name(&self) -> String {
+        match self {
+            LoxFunction::UserFunction{def_name, class_name: Some(class_name), .. } => format!("{}.{}", class_name, def_name),
+            LoxFunction::BoundMethod(_, method) => method.qualified_name(),
+            _ => self.name().to_string(),
         }
     }
 
@@ -86,78 +122,65 @@ impl LoxFunction {
             panic!("Arity should be checked before invoking");
         }
         match self {
-            LoxFunction::UserFunction{ params, body, closure, is_initializer, .. } => {
-                // create a new environment for the function call
-                let global = interpreter.env.global.clone();
-                let closure = closure.clone();
-                let old_call_stack = interpreter.env.call_stack;
-                let env = Environment::from(old_call_stack + 1, global, closure)?;
-               
-                let old_env = interpreter.change_env(env);
-                // enter a new scope
-                interpreter.env.enter_scope();
-                // bind parameters to arguments
-                for (param, arg) in params.iter().zip(arguments.iter()) {
-                    interpreter.env.define(param, arg.clone());
-                }
-                // evaluate the function body
-                let result = interpreter.execute_block(body);
-                // exit the scope
-                interpreter.env.exit_scope();
-                let closure = interpreter.change_env(old_env);
-                // return the result
-                // if the result is a return statement, return the value
-                match result {
-                    Ok(_) => {
-                        if *is_initializer {
-                            // if the function is an initializer, always return this
-                            let this_token = Token::new(TokenType::This, "this".to_string(), 0);
-                            let this = closure.get_by_depth(&this_token, 0).unwrap();
-                            Ok(this)
-                        } else {
-                            Ok(LoxValue::Null)
-                        }
-                    },
-                    Err(RloxError::ReturnValue(value)) => {
-                        // return the value
-                        if *is_initializer {
-                            // if the function is an initializer, always return this
-                            let this_token = Token::new(TokenType::This, "this".to_string(), 0);
-                            let this = closure.get_by_depth(&this_token, 0).unwrap();
-                            Ok(this)
-                        } else {
-                            Ok(value)
-                        }
-                    },
-                    Err(e) => {
-                        // return the error
-                        Err(e)
-                    }
-                }
-            },
-            LoxFunction::BuiltInFunction(_, implementation) => {
+            LoxFunction::UserFunction{ .. } => self.invoke_user(interpreter, None, arguments),
+            LoxFunction::BoundMethod(receiver, method) => method.invoke_user(interpreter, Some(receiver), arguments),
+            LoxFunction::BuiltInFunction(_, _, implementation) => {
                 // invoke built-in function
                 implementation(arguments)
             },
+            LoxFunction::BoundBuiltIn(receiver, _, _, implementation) => {
+                // the receiver becomes the first argument
+                let mut all_arguments = vec![(**receiver).clone()];
+                all_arguments.extend(arguments);
+                implementation(all_arguments)
+            },
         }
     }
 
-    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
-        match self {
-            LoxFunction::UserFunction { def_name, params, body, closure , is_initializer} => {
-                // eprintln!("old closure: {:?}", closure);
-                let mut new_closure = EnvItem::from_parent(Rc::clone(&closure));
-                new_closure.table.insert("this".to_string(), LoxValue::Instance(instance));
-                LoxFunction::UserFunction {
-                    def_name: def_name.clone(),
-                    params: params.clone(),
-                    body: Rc::clone(body),
-                    closure: Rc::new(RefCell::new(new_closure)),
-                    is_initializer: *is_initializer,
-                }
-            }
-            _ => panic!("Cannot bind a built-in function"),
+    /// Runs a user function. A method is given the instance it is called on, which
+    /// takes the first slot of its scope, ahead of the parameters.
+    fn invoke_user(&self, interpreter: &mut Interpreter, this: Option<&Rc<RefCell<LoxInstance>>>, arguments: Vec<LoxValue>) -> Result<LoxValue, RloxError> {
+        let LoxFunction::UserFunction{ def_name, params, body, closure, is_initializer, .. } = self else {
+            unreachable!("Only user functions have a body to run");
+        };
+        // create a new environment for the function call
+        let global = interpreter.env.global.clone();
+        let closure = closure.clone();
+        let old_call_stack = interpreter.env.call_stack;
+        let env = Environment::from(old_call_stack + 1, global, closure)?;
+
+        let old_env = interpreter.change_env(env);
+        // enter a new scope
+        interpreter.env.enter_scope();
+        if let Some(this) = this {
+            interpreter.env.define(Symbol::intern("this"), LoxValue::Instance(Rc::clone(this)));
         }
+        // bind parameters to arguments
+        for (param, arg) in params.iter().zip(arguments) {
+            interpreter.env.define(param.clone(), arg);
+        }
+        // evaluate the function body
+        interpreter.frames.push(CallFrame { name: def_name.clone(), line: 0 });
+        let result = interpreter.execute_block(body);
+        interpreter.frames.pop();
+        // exit the scope
+        interpreter.env.exit_scope();
+        interpreter.change_env(old_env);
+        // return the result
+        // if the result is a return statement, return the value
+        match (result, this) {
+            // if the function is an initializer, always return this
+            (Ok(_) | Err(RloxError::ReturnValue(_)), Some(this)) if *is_initializer => Ok(LoxValue::Instance(Rc::clone(this))),
+            (Ok(_), _) => Ok(LoxValue::Null),
+            (Err(RloxError::ReturnValue(value)), _) => Ok(value),
+            // return the error
+            (Err(e), _) => Err(e),
+        }
+    }
+
+    /// Binds a method to the instance it is looked up on.
+    pub fn bind(self: &Rc<Self>, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        LoxFunction::BoundMethod(instance, Rc::clone(self))
     }
 }
 
@@ -0,0 +1,68 @@
+//! This file will test the benchmark runner
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rlox::bench::{compare, load_baseline, run, save_baseline, BenchOptions, Measurement};
+use rstest::rstest;
+
+/// A fresh directory holding the given scripts.
+fn scripts(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rlox-bench-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (file, source) in files {
+        fs::write(dir.join(file), source).unwrap();
+    }
+    dir
+}
+
+fn measurement(seconds: f64, allocations: usize) -> Measurement {
+    Measurement { name: "fib".to_string(), time: Duration::from_secs_f64(seconds), allocations }
+}
+
+#[rstest]
+#[case::same(measurement(1.0, 100), false, "+0.0% time, +0.0% allocations")]
+#[case::faster(measurement(0.5, 90), false, "-50.0% time, -10.0% allocations")]
+#[case::within_threshold(measurement(1.05, 100), false, "+5.0% time, +0.0% allocations")]
+#[case::slower(measurement(1.2, 100), true, "+20.0% time, +0.0% allocations  REGRESSION")]
+#[case::more_allocations(measurement(1.0, 150), true, "+0.0% time, +50.0% allocations  REGRESSION")]
+fn test_compare(#[case] new: Measurement, #[case] regressed: bool, #[case] text: &str) {
+    assert_eq!(compare(&new, &measurement(1.0, 100), 10.0), (text.to_string(), regressed));
+}
+
+#[test]
+fn test_baseline() {
+    let dir = scripts("baseline", &[]);
+    let path = dir.join("baseline.json");
+    let saved = vec![measurement(0.25, 1000)];
+    save_baseline(&path, &saved).unwrap();
+    let loaded = load_baseline(&path).unwrap();
+    fs::write(&path, "[1, 2]").unwrap();
+    let invalid = load_baseline(&path);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(loaded, saved);
+    assert!(invalid.is_err());
+}
+
+#[test]
+fn test_run() {
+    let dir = scripts("run", &[("ok.lox", "print 1 + 2;"), ("broken.lox", "print nil + 1;"), ("notes.txt", "")]);
+    let baseline = dir.join("baseline.json");
+    let options = BenchOptions { runs: 1, save: Some(baseline.clone()), ..BenchOptions::default() };
+    let mut output = vec![];
+    let ok = run(&dir, &options, &mut output).unwrap();
+    let saved = load_baseline(&baseline).unwrap();
+
+    // with a huge threshold, timing noise never counts as a regression
+    let options = BenchOptions { runs: 1, baseline: Some(baseline), threshold: 1e9, ..BenchOptions::default() };
+    let mut compared = vec![];
+    run(Path::new(&dir), &options, &mut compared).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(!ok, "a failing benchmark fails the run");
+    assert!(output.contains("broken.lox stopped with a runtime error."), "{}", output);
+    assert_eq!(saved.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), vec!["ok"]);
+    assert!(String::from_utf8(compared).unwrap().contains("% time, "));
+}
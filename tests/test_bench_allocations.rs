@@ -0,0 +1,31 @@
+//! This file will test the allocation counts of the benchmark runner.
+//! It holds a single test, so that no other test allocates while it counts.
+
+use std::fs;
+
+use rlox::bench::{measure, CountingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_measure() {
+    let dir = std::env::temp_dir().join(format!("rlox-bench-measure-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let empty = dir.join("empty.lox");
+    let strings = dir.join("strings.lox");
+    fs::write(&empty, "").unwrap();
+    fs::write(&strings, "var s = \"\";\nfor (var i = 0; i < 100; i = i + 1) s = s + \"x\";\nprint s;").unwrap();
+
+    // the first run interns the names the script uses, so the counts settle after it
+    measure(&strings, 1).unwrap();
+    let first = measure(&strings, 1).unwrap();
+    let second = measure(&strings, 1).unwrap();
+    let empty = measure(&empty, 1).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(first.name, "strings");
+    assert_eq!(first.allocations, second.allocations, "counts are repeatable");
+    // each concatenation allocates a new string
+    assert!(first.allocations >= empty.allocations + 100, "{:?} {:?}", first, empty);
+}
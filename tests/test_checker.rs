@@ -0,0 +1,67 @@
+//! This file will test the gradual type checker
+
+use rlox::checker::Checker;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rstest::rstest;
+
+/// Checks `source` and returns the error messages.
+fn check(source: &str) -> Vec<String> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse().unwrap();
+    assert!(!parser.had_error);
+
+    let mut checker = Checker::new();
+    checker.silent = true;
+    checker.check_program(&program);
+    checker.errors.iter().map(|error| error.message()).collect()
+}
+
+#[rstest]
+#[case::annotated("var a: Number = 1; var b: String = \"b\"; var c: Bool = !a; var d: Nil = nil;")]
+#[case::any("var a: Any = 1; a = \"now a string\";")]
+#[case::unannotated_stays_dynamic("var a = 1; a = \"s\"; print a - 1; print \"hi\" - 1;")]
+#[case::inferred_operators("var a: Number = (1 + 2) * 3 % 4; var b: String = \"a\" + \"b\"; var c: Bool = 1 < 2;")]
+#[case::interpolation("var a: String = \"${1 + 2}\";")]
+#[case::function_result("fun f(): Number { return 1; }\nvar a: Number = f() + 1;")]
+#[case::unannotated_function("fun f() { return 1; }\nvar a: String = f();")]
+#[case::declared_later("fun f(): Number { return g(\"x\"); }\nfun g(s: String): Number { return len(s); }")]
+#[case::subclass("class A {}\nclass B < A {}\nvar a: A = B();")]
+#[case::nil_instance("class A {}\nvar a: A = nil;")]
+#[case::function_values("fun f() {}\nclass A {}\nvar g: Function = f; var h: Function = A;")]
+#[case::method_result("class A { size(): Number { return 1; } }\nvar n: Number = A().size() + 1;")]
+#[case::this("class A { init(n: Number) { this.n = n; } twice(): A { return this; } }")]
+#[case::no_return_annotation_check("fun f(a: Number) { return \"s\"; }")]
+#[case::local_class("{ class Local {}\n  var l: Local = Local(); }")]
+#[case::reassigned_declarations("fun f(){return 1;} f = 2; print f; class A {} A = nil; print A;")]
+#[case::unannotated_function_operand("fun g(){return 1;} if (false) { print g + 1; } print \"ok\";")]
+#[case::unannotated_this("class A { m() { if (false) print this + 1; } }")]
+#[case::field_hides_method("class A { init() { this.size = 3; } size() { return 1; } twice() { return this.size * 2; } }\nvar n: Number = A().size;")]
+fn test_well_typed(#[case] source: &str) {
+    assert_eq!(check(source), Vec::<String>::new());
+}
+
+#[rstest]
+#[case::var("var a: Number = \"one\";", "Cannot assign a value of type 'String' to 'a' of type 'Number'.")]
+#[case::assign("var a: Bool = true;\na = nil;", "Cannot assign a value of type 'Nil' to 'a' of type 'Bool'.")]
+#[case::parameter_assign("fun f(a: String) { a = 1; }", "Cannot assign a value of type 'Number' to 'a' of type 'String'.")]
+#[case::argument("fun f(a, b: Number) {}\nf(1, \"two\");", "Argument 2 of 'f' must be of type 'Number', found 'String'.")]
+#[case::constructor("class P { init(x: Number) {} }\nP(true);", "Argument 1 of 'P' must be of type 'Number', found 'Bool'.")]
+#[case::method("class P { move(dx: Number) {} }\nP().move(\"left\");", "Argument 1 of 'move' must be of type 'Number', found 'String'.")]
+#[case::inherited_method("class A { m(x: Number) {} }\nclass B < A {}\nB().m(nil);", "Argument 1 of 'm' must be of type 'Number', found 'Nil'.")]
+#[case::return_value("fun f(): String { return 1; }", "Cannot return a value of type 'Number' from a function that returns 'String'.")]
+#[case::return_nothing("fun f(): Number { return; }", "Cannot return a value of type 'Nil' from a function that returns 'Number'.")]
+#[case::superclass("class A {}\nclass B < A {}\nvar b: B = A();", "Cannot assign a value of type 'A' to 'b' of type 'B'.")]
+#[case::unknown_type("var a: Strng;", "Unknown type 'Strng'.")]
+#[case::unknown_parameter_type("fun f(a: Numbr) {}", "Unknown type 'Numbr'.")]
+#[case::operand("var s: String = \"s\";\nprint s - 1;", "Operands of '-' must be numbers, found 'String' and 'Number'.")]
+#[case::comparison("var s: String = \"s\";\nprint s < \"t\";", "Operands of '<' must be numbers, found 'String' and 'String'.")]
+#[case::negate("var b: Bool = true;\nprint -b;", "Operand of '-' must be a number, found 'Bool'.")]
+#[case::add("fun f(): Number { return 1; }\nprint \"n = \" + f();", "Operands of '+' must be two numbers or two strings, found 'String' and 'Number'.")]
+#[case::add_bool("var b: Bool = true;\nprint b + x;", "Operands of '+' must be two numbers or two strings, found 'Bool' and 'Any'.")]
+#[case::call("var n: Number = 1;\nn();", "Can only call functions and classes, found 'Number'.")]
+fn test_mismatch(#[case] source: &str, #[case] expected: &str) {
+    assert_eq!(check(source), vec![expected.to_string()]);
+}
@@ -0,0 +1,106 @@
+//! This file will test line and branch coverage and the LCOV report
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rlox::coverage::Coverage;
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+
+const SOURCE: &str = "\
+fun classify(n) {
+  if (n < 0) {
+    return \"negative\";
+  } else {
+    return \"non-negative\";
+  }
+}
+var i = 0;
+while (i < 2 and true) {
+  print classify(i);
+  i = i + 1;
+}
+fun unused() {
+  print \"never\" or false;
+}
+";
+
+fn cover(source: &str) -> Coverage {
+    let mut interpreter = Interpreter::new();
+    interpreter.output = Box::new(std::io::sink());
+    let tokens = Scanner::new(source.to_string()).scan_tokens();
+    let program = Parser::new(tokens).parse().unwrap();
+    Resolver::new(&mut interpreter).resolve_program(&program);
+
+    let coverage = Rc::new(RefCell::new(Coverage::new(&program)));
+    interpreter.hook = Some(Box::new(Rc::clone(&coverage)));
+    interpreter.interpret(program);
+    interpreter.hook = None;
+    Rc::try_unwrap(coverage).unwrap().into_inner()
+}
+
+#[test]
+fn test_lines_and_branches() {
+    let coverage = cover(SOURCE);
+    assert_eq!(coverage.lines.get(&3), Some(&0), "the negative case never runs");
+    assert_eq!(coverage.lines.get(&5), Some(&2));
+    assert_eq!(coverage.lines.get(&14), Some(&0), "unused functions are still listed");
+    assert_eq!(coverage.lines.get(&4), None, "no statement starts at `else`");
+
+    // branches are keyed by the column of their keyword or operator
+    assert_eq!(coverage.branches[&(2, 3)], [0, 2]);
+    assert_eq!(coverage.branches[&(9, 1)], [2, 1]);
+    // `and` at column 14 short-circuits once, when the loop ends
+    assert_eq!(coverage.branches[&(9, 14)], [2, 1]);
+    assert_eq!(coverage.branches[&(14, 17)], [0, 0]);
+    assert_eq!(coverage.summary(), "lines: 8/10 (80.0%), branches: 5/8 (62.5%)");
+}
+
+#[test]
+fn test_branches_sharing_a_line() {
+    let coverage = cover("\
+var x = 1;
+if (x > 0) print 1; if (x > 5) print 2;
+for (var i = 0; i < 3; i = i + 1) if (i == 1) print i;
+");
+    assert_eq!(coverage.branches[&(2, 1)], [1, 0]);
+    assert_eq!(coverage.branches[&(2, 21)], [0, 1]);
+    assert_eq!(coverage.branches[&(3, 1)], [3, 1]);
+    assert_eq!(coverage.branches[&(3, 35)], [1, 2]);
+    assert_eq!(coverage.branches.len(), 4);
+}
+
+#[test]
+fn test_lcov_report() {
+    let mut report = vec![];
+    cover(SOURCE).write_lcov("script.lox", &mut report).unwrap();
+    let expected = "TN:
+SF:script.lox
+BRDA:2,0,0,0
+BRDA:2,0,1,2
+BRDA:9,0,0,2
+BRDA:9,0,1,1
+BRDA:9,1,0,2
+BRDA:9,1,1,1
+BRDA:14,0,0,-
+BRDA:14,0,1,-
+BRF:8
+BRH:5
+DA:1,1
+DA:2,2
+DA:3,0
+DA:5,2
+DA:8,1
+DA:9,1
+DA:10,2
+DA:11,2
+DA:13,1
+DA:14,0
+LF:10
+LH:8
+end_of_record
+";
+    assert_eq!(String::from_utf8(report).unwrap(), expected);
+}
@@ -0,0 +1,89 @@
+//! This file will test the debug adapter by replaying recorded transcripts.
+//!
+//! A transcript lists client messages (`-> `) and the messages the adapter
+//! is expected to send back (`<- `). Expected messages only need to contain
+//! a subset of the fields of the real ones; lines starting with `#` are comments.
+
+use std::cell::RefCell;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use serde_json::{json, Value};
+use walkdir::WalkDir;
+
+use rlox::dap::run_adapter;
+use rlox::rpc::{read_message, write_message};
+
+/// A writer whose contents can still be read after it was handed out.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Checks that every field of `expected` is present in `actual` with the same value.
+/// Arrays must have the same length and match element by element.
+fn matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected.iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|other| matches(value, other))),
+        (Value::Array(expected), Value::Array(actual)) => expected.len() == actual.len()
+            && expected.iter().zip(actual).all(|(e, a)| matches(e, a)),
+        _ => expected == actual,
+    }
+}
+
+fn replay(path: &PathBuf) {
+    let transcript = fs::read_to_string(path).unwrap();
+    let mut input = vec![];
+    let mut expected = vec![];
+    let mut seq = 0;
+    for line in transcript.lines() {
+        if let Some(message) = line.strip_prefix("-> ") {
+            seq += 1;
+            let mut message: Value = serde_json::from_str(message).unwrap();
+            message["seq"] = json!(seq);
+            write_message(&mut input, &message).unwrap();
+        } else if let Some(message) = line.strip_prefix("<- ") {
+            expected.push(serde_json::from_str::<Value>(message).unwrap());
+        }
+    }
+
+    let output = SharedBuffer::default();
+    run_adapter(Cursor::new(input), output.clone()).unwrap();
+    let mut actual = vec![];
+    let mut reader = Cursor::new(output.0.take());
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        actual.push(message);
+    }
+
+    for (i, expected) in expected.iter().enumerate() {
+        let actual = actual.get(i).unwrap_or(&Value::Null);
+        assert!(matches(expected, actual), "{}: message {} differs\nexpected: {}\n  actual: {}",
+            path.display(), i, expected, actual);
+    }
+    assert_eq!(actual.len(), expected.len(), "{}: unexpected extra messages: {:?}",
+        path.display(), &actual[expected.len().min(actual.len())..]);
+}
+
+#[test]
+fn test_dap_transcripts() {
+    let transcripts: Vec<PathBuf> = WalkDir::new("tests/dap").into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "transcript"))
+        .collect();
+    assert!(!transcripts.is_empty());
+    for path in transcripts {
+        replay(&path);
+    }
+}
@@ -0,0 +1,121 @@
+//! This file will test the step debugger with scripted command sessions
+
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::rc::Rc;
+
+use rlox::debugger::{ConsoleDebugger, StepMode, Stepper};
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+
+/// A writer whose contents can still be read after it was handed out.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+const SOURCE: &str = "\
+fun add(a, b) {
+  var sum = a + b;
+  return sum;
+}
+var x = 1;
+print add(x, 2);
+for (var i = 0; i < 2; i = i + 1) {
+  print i;
+}
+print \"done\";
+";
+
+/// Runs `SOURCE` under the debugger and returns the combined transcript.
+fn debug(commands: &str) -> String {
+    let buffer = SharedBuffer::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.output = Box::new(buffer.clone());
+
+    let tokens = Scanner::new(SOURCE.to_string()).scan_tokens();
+    let program = Parser::new(tokens).parse().unwrap();
+    Resolver::new(&mut interpreter).resolve_program(&program);
+
+    let debugger = ConsoleDebugger::new(SOURCE, Cursor::new(commands.to_string()), buffer.clone());
+    interpreter.hook = Some(Box::new(debugger));
+    interpreter.interpret(program);
+
+    let output = buffer.0.borrow();
+    String::from_utf8_lossy(&output).replace("(rlox-debug) ", "")
+}
+
+#[test]
+fn test_stepper() {
+    let mut stepper = Stepper::new(StepMode::Continue);
+    stepper.breakpoints.insert(3);
+    assert!(!stepper.should_pause(2, 1));
+    assert!(stepper.should_pause(3, 1));
+    // a second statement on the same line does not hit again
+    assert!(!stepper.should_pause(3, 1));
+    assert!(!stepper.should_pause(4, 1));
+    assert!(stepper.should_pause(3, 1));
+
+    stepper.breakpoints.clear();
+    stepper.mode = StepMode::StepOver(1);
+    assert!(!stepper.should_pause(5, 2));
+    assert!(stepper.should_pause(6, 1));
+    stepper.mode = StepMode::StepOut(2);
+    assert!(!stepper.should_pause(7, 2));
+    assert!(stepper.should_pause(8, 1));
+}
+
+#[test]
+fn test_breakpoint_and_inspection() {
+    let transcript = debug("b 2\nc\nbt\nlocals\np a * 10 + b\nc\n");
+    let expected = "   1 | fun add(a, b) {
+Breakpoint set at line 2
+   2 |   var sum = a + b;
+#0 add at line 2
+#1 <script> at line 6
+a = 1
+b = 2
+12
+3
+0
+1
+done
+";
+    assert_eq!(transcript, expected);
+}
+
+#[test]
+fn test_stepping() {
+    let transcript = debug("n\nn\ns\ns\nlocals\nfinish\nn\nq\n");
+    let expected = "   1 | fun add(a, b) {
+   5 | var x = 1;
+   6 | print add(x, 2);
+   2 |   var sum = a + b;
+   3 |   return sum;
+a = 1
+b = 2
+sum = 3
+3
+   7 | for (var i = 0; i < 2; i = i + 1) {
+   7 | for (var i = 0; i < 2; i = i + 1) {
+RuntimeError: Program stopped by debugger.
+";
+    assert_eq!(transcript, expected);
+}
+
+#[test]
+fn test_breakpoint_in_loop() {
+    let transcript = debug("b 8\nc\np i\nc\np i\nc\n");
+    assert!(transcript.contains("   8 |   print i;\n0\n0\n   8 |   print i;\n1\n1\ndone\n"), "{}", transcript);
+}
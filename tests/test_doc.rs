@@ -0,0 +1,76 @@
+//! This file will test the documentation generator on the sources in tests/doc
+
+use std::fs;
+use std::path::Path;
+
+use rlox::doc::{generate, render, ClassDoc, Format, FunctionDoc, ModuleDoc, Page};
+use rstest::rstest;
+
+fn page(source: &str, name: &str) -> Page {
+    let text = fs::read_to_string(Path::new("tests/doc").join(source)).unwrap();
+    Page { source: source.to_string(), name: name.to_string(), module: ModuleDoc::from_source(&text).unwrap() }
+}
+
+fn pages() -> Vec<Page> {
+    vec![page("shapes/base.lox", "shapes.base"), page("shapes/circle.lox", "shapes.circle"), page("util.lox", "util")]
+}
+
+#[test]
+fn test_collect_declarations() {
+    let module = ModuleDoc::from_source(&fs::read_to_string("tests/doc/util.lox").unwrap()).unwrap();
+    assert_eq!(module.functions, vec![
+        FunctionDoc { name: "add".to_string(), params: vec!["a".to_string(), "b".to_string()], returns: None, doc: Some("Adds two numbers.".to_string()), line: 2 },
+        FunctionDoc { name: "undocumented".to_string(), params: vec![], returns: None, doc: None, line: 6 },
+    ]);
+    assert_eq!(module.classes, vec![ClassDoc {
+        name: "Remote".to_string(),
+        superclass: Some("Elsewhere".to_string()),
+        doc: Some("Extends a class from outside the documented files.".to_string()),
+        line: 9,
+        methods: vec![],
+    }]);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(ModuleDoc::from_source("fun broken( {").is_err());
+}
+
+#[rstest]
+#[case::index("index.md", "- [`Circle`](shapes.circle.md#class-Circle) — A circle around the origin.")]
+#[case::undocumented_in_index("index.md", "- [`undocumented`](util.md#fun-undocumented)\n")]
+#[case::superclass_link("shapes.circle.md", "### `class Circle` < [`Shape`](shapes.base.md#class-Shape)")]
+#[case::subclass_link("shapes.base.md", "Subclasses: [`Circle`](shapes.circle.md#class-Circle)")]
+#[case::unknown_superclass("util.md", "### `class Remote` < `Elsewhere`")]
+#[case::method("shapes.circle.md", "<a id=\"Circle-init\"></a>\n\n#### `init(radius)`\n\nMakes a circle with the given radius.\n")]
+#[case::paragraphs("shapes.base.md", "Something that can be drawn.\n\nSubclasses override `area`.\n")]
+#[case::function("util.md", "### `fun add(a, b)`\n\nAdds two numbers.\n")]
+fn test_render_markdown(#[case] file: &str, #[case] expected: &str) {
+    let files = render(&pages(), Format::Markdown);
+    let (_, content) = files.iter().find(|(name, _)| name == file).unwrap();
+    assert!(content.contains(expected), "{} does not contain {:?}:\n{}", file, expected, content);
+}
+
+#[rstest]
+#[case::superclass_link("shapes.circle.html", "<h3 id=\"class-Circle\"><code>class Circle</code> &lt; <a href=\"shapes.base.html#class-Shape\"><code>Shape</code></a></h3>")]
+#[case::escaped_doc("shapes.circle.html", "<p>A circle with a hole &lt;inside&gt;.</p>")]
+#[case::index_link("index.html", "<li><a href=\"util.html\">util.lox</a></li>")]
+fn test_render_html(#[case] file: &str, #[case] expected: &str) {
+    let files = render(&pages(), Format::Html);
+    let (_, content) = files.iter().find(|(name, _)| name == file).unwrap();
+    assert!(content.contains(expected), "{} does not contain {:?}:\n{}", file, expected, content);
+}
+
+#[test]
+fn test_generate() {
+    let out = std::env::temp_dir().join(format!("rlox-doc-{}", std::process::id()));
+    let mut output = vec![];
+    assert!(generate(Path::new("tests/doc"), &out, Format::Markdown, &mut output).unwrap());
+    let mut written: Vec<String> = fs::read_dir(&out).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    written.sort();
+    fs::remove_dir_all(&out).unwrap();
+    assert_eq!(written, vec!["index.md", "shapes.base.md", "shapes.circle.md", "util.md"]);
+    assert!(String::from_utf8(output).unwrap().starts_with("Wrote 4 pages to "));
+}
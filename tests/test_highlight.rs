@@ -0,0 +1,52 @@
+//! This file will test the syntax highlighter and the trivia the scanner keeps for it
+
+use rlox::highlight::{highlight, tokenize, Format};
+use rstest::rstest;
+use walkdir::WalkDir;
+
+/// Removes the ANSI escape codes from highlighted text.
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+#[test]
+fn test_tokens_reproduce_sources() {
+    for entry in WalkDir::new("testcases").into_iter().chain(WalkDir::new("tests")) {
+        let path = entry.unwrap().into_path();
+        if path.extension().is_none_or(|ext| ext != "lox") {
+            continue;
+        }
+        let source = std::fs::read_to_string(&path).unwrap();
+        let lexemes: String = tokenize(&source).iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(lexemes, source, "{}", path.display());
+        assert_eq!(strip_ansi(&highlight(&source, Format::Ansi)), source, "{}", path.display());
+    }
+}
+
+#[rstest]
+#[case::keywords("var x = nil;", "\x1b[1;35mvar\x1b[0m x \x1b[33m=\x1b[0m \x1b[1;35mnil\x1b[0m;")]
+#[case::literals("f(\"a\", 0x1F);", "f(\x1b[32m\"a\"\x1b[0m, \x1b[36m0x1F\x1b[0m);")]
+#[case::comments("a /* b */ // c", "a \x1b[90m/* b */\x1b[0m \x1b[90m// c\x1b[0m")]
+#[case::interpolation("\"${a}\"", "\x1b[32m\"${\x1b[0ma\x1b[32m}\"\x1b[0m")]
+#[case::malformed("1 ~ 2", "\x1b[36m1\x1b[0m \x1b[4;31m~\x1b[0m \x1b[36m2\x1b[0m")]
+#[case::unterminated_comment("/* open\nstill", "\x1b[4;31m/* open\nstill\x1b[0m")]
+fn test_highlight_ansi(#[case] source: &str, #[case] expected: &str) {
+    assert_eq!(highlight(source, Format::Ansi), expected);
+}
+
+#[rstest]
+#[case::classes("print a < 1;", "<pre class=\"lox\"><code><span class=\"lox-keyword\">print</span> <span class=\"lox-identifier\">a</span> <span class=\"lox-operator\">&lt;</span> <span class=\"lox-number\">1</span><span class=\"lox-punctuation\">;</span></code></pre>\n")]
+#[case::escaped("\"<&>\"", "<pre class=\"lox\"><code><span class=\"lox-string\">\"&lt;&amp;&gt;\"</span></code></pre>\n")]
+#[case::doc_comment("/// x\n", "<pre class=\"lox\"><code><span class=\"lox-comment\">/// x</span>\n</code></pre>\n")]
+fn test_highlight_html(#[case] source: &str, #[case] expected: &str) {
+    assert_eq!(highlight(source, Format::Html), expected);
+}
@@ -0,0 +1,30 @@
+//! This file will test interned symbols and shared strings
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rlox::intern::Symbol;
+use rlox::value::LoxValue;
+
+#[test]
+fn test_symbols() {
+    let a = Symbol::intern("name");
+    let b = Symbol::intern(&String::from("name"));
+    assert_eq!(a, b);
+    assert_ne!(a, Symbol::intern("other"));
+    assert_eq!(a.as_str(), "name");
+
+    // maps keyed by symbols are searched by text
+    let map = HashMap::from([(a, 1)]);
+    assert_eq!(map.get("name"), Some(&1));
+}
+
+#[test]
+fn test_strings_are_shared() {
+    let s = LoxValue::String("shared".into());
+    let copy = s.clone();
+    let (LoxValue::String(a), LoxValue::String(b)) = (&s, &copy) else { panic!("expected strings") };
+    assert!(Rc::ptr_eq(a, b));
+    assert_eq!(s, copy);
+    assert_eq!(s, LoxValue::String("shared".into()));
+}
@@ -23,9 +23,11 @@ fn find_test_cases() -> Vec<(PathBuf, PathBuf)> {
 }
 
 
-fn run_and_capture(path: &str) -> Result<String, String> {
+fn run_and_capture(path: &str, flags: &[&str]) -> Result<String, String> {
     let output = Command::new("cargo")
-        .args(["run", "--quiet", "--", path])
+        .args(["run", "--quiet", "--"])
+        .args(flags)
+        .arg(path)
         .output()
         .expect("Failed to run test");
 
@@ -39,6 +41,16 @@ fn run_and_capture(path: &str) -> Result<String, String> {
 
 #[test]
 fn lox_test() {
+    check_test_cases(&[]);
+}
+
+/// The optimizer must not change what any of the test cases print.
+#[test]
+fn lox_test_optimized() {
+    check_test_cases(&["-O"]);
+}
+
+fn check_test_cases(flags: &[&str]) {
     let cases = find_test_cases()
         .into_iter()
         .map(|(input, output)| (input.to_string_lossy().into_owned(), output.to_string_lossy().into_owned()))
@@ -46,7 +58,7 @@ fn lox_test() {
     eprintln!("Testing {} tests!", cases.len());
     for (input_path, expected_path) in cases {
         eprintln!("Testing `{}`", input_path);
-        match run_and_capture(&input_path) {
+        match run_and_capture(&input_path, flags) {
             Ok(stdout) => {
                 let normalize = |s: &str| s.trim_end().replace("\r\n", "\n");
                 let mut expected = String::new();
@@ -14,8 +14,8 @@ use rstest::rstest;
 #[case::literal("nil", LoxValue::Null)]
 #[case::literal("true", LoxValue::Boolean(true))]
 #[case::literal("false", LoxValue::Boolean(false))]
-#[case::literal("\"hello\"", LoxValue::String("hello".to_string()))]
-#[case::literal("\"hello \\\"world\\\"\"", LoxValue::String("hello \"world\"".to_string()))]
+#[case::literal("\"hello\"", LoxValue::String("hello".into()))]
+#[case::literal("\"hello \\\"world\\\"\"", LoxValue::String("hello \"world\"".into()))]
 // test simple unary expressions
 #[case::simple_unary("--3.6", LoxValue::Number(3.6))]
 #[case::simple_unary("-1234.1234", LoxValue::Number(-1234.1234))]
@@ -30,7 +30,7 @@ use rstest::rstest;
 #[case::simple_binary("1234.1234 * 1234.1234", LoxValue::Number(1234.1234 * 1234.1234))]
 #[case::simple_binary("123.4 / 1234.1234", LoxValue::Number(123.4 / 1234.1234))]
 #[case::simple_binary("1234.1234 + 2123.21", LoxValue::Number(1234.1234 + 2123.21))]
-#[case::simple_binary("\"hello\" + \" \" + \"world\" + \" \" + \"rlox\"", LoxValue::String("hello world rlox".to_string()))]
+#[case::simple_binary("\"hello\" + \" \" + \"world\" + \" \" + \"rlox\"", LoxValue::String("hello world rlox".into()))]
 #[case::simple_binary("1234.1234 == 1234.1234", LoxValue::Boolean(true))]
 #[case::simple_binary("1234.1234 != 1234.1234", LoxValue::Boolean(false))]
 #[case::simple_binary("1234.1235 > 1234.1234", LoxValue::Boolean(true))]
@@ -50,7 +50,7 @@ use rstest::rstest;
 #[case::negated_group("-((5 + 2) * 3)", LoxValue::Number(-21.0))]
 #[case::bang_on_comparison("!((5 > 3) == false)", LoxValue::Boolean(true))]
 #[case::double_bang_eq("!!((1 + 1) == 2)", LoxValue::Boolean(true))]
-#[case::string_concat_group("\"hello \" + (\"world\" + \"!\")", LoxValue::String("hello world!".to_string()))]
+#[case::string_concat_group("\"hello \" + (\"world\" + \"!\")", LoxValue::String("hello world!".into()))]
 #[case::deep_logical_expr("((true == true) != false) == true", LoxValue::Boolean(true))]
 #[case::bracket_comparisons("(123 + 456) > (100 + 200)", LoxValue::Boolean(true))]
 #[case::nested_comparison_eq("123 == (100 + 23)", LoxValue::Boolean(true))]
@@ -67,3 +67,74 @@ fn test_expr(#[case] source: &str, #[case] expected: LoxValue) {
     let result = expression.accept(&mut interpreter).unwrap();
     assert_eq!(result, expected);
 }
+
+/// Test cases for the modulo operator and the math library
+#[rstest]
+#[case::modulo("7 % 3", LoxValue::Number(1.0))]
+#[case::modulo_negative("-7 % 3", LoxValue::Number(-1.0))]
+#[case::modulo_fraction("5.5 % 2", LoxValue::Number(1.5))]
+#[case::modulo_precedence("1 + 7 % 4 * 2", LoxValue::Number(7.0))]
+#[case::div("div(7, 2)", LoxValue::Number(3.0))]
+#[case::div_negative("div(-7, 2)", LoxValue::Number(-3.0))]
+#[case::div_and_modulo("div(-7, 3) * 3 + -7 % 3", LoxValue::Number(-7.0))]
+#[case::sqrt("sqrt(16)", LoxValue::Number(4.0))]
+#[case::pow("pow(2, 10)", LoxValue::Number(1024.0))]
+#[case::abs("abs(-2.5)", LoxValue::Number(2.5))]
+#[case::floor("floor(-2.5)", LoxValue::Number(-3.0))]
+#[case::ceil("ceil(2.1)", LoxValue::Number(3.0))]
+#[case::round("round(2.5)", LoxValue::Number(3.0))]
+#[case::min("min(3, -1)", LoxValue::Number(-1.0))]
+#[case::max("max(3, -1)", LoxValue::Number(3.0))]
+#[case::sin("sin(0)", LoxValue::Number(0.0))]
+#[case::cos("cos(PI)", LoxValue::Number(-1.0))]
+#[case::tan("tan(0)", LoxValue::Number(0.0))]
+#[case::atan2("atan2(1, 1) * 4 == PI", LoxValue::Boolean(true))]
+#[case::log("log(E)", LoxValue::Number(1.0))]
+#[case::exp("exp(0)", LoxValue::Number(1.0))]
+fn test_math(#[case] source: &str, #[case] expected: LoxValue) {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let expression = parser.parse_expr().unwrap();
+    let mut interpreter = Interpreter::new();
+    let result = expression.accept(&mut interpreter).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[rstest]
+#[case::modulo_by_zero("1 % 0", "RuntimeError: Division by zero.")]
+#[case::modulo_string("\"a\" % 2", "RuntimeError: Operands must be two numbers.")]
+#[case::div_by_zero("div(1, 0)", "RuntimeError: div(): Division by zero")]
+#[case::sqrt_string("sqrt(\"4\")", "RuntimeError: sqrt(): Need a number argument")]
+#[case::pow_nil("pow(2, nil)", "RuntimeError: pow(): Need number arguments")]
+fn test_math_errors(#[case] source: &str, #[case] expected: &str) {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let expression = parser.parse_expr().unwrap();
+    let mut interpreter = Interpreter::new();
+    let error = expression.accept(&mut interpreter).unwrap_err();
+    assert_eq!(error.to_string(), expected);
+}
+
+#[rstest]
+#[case::unknown_method("\"abc\".size()", "RuntimeError: Undefined property 'size'.")]
+#[case::no_global_method("\"abc\".chr()", "RuntimeError: Undefined property 'chr'.")]
+#[case::number_receiver("(1).len()", "RuntimeError: Only instances have properties.")]
+#[case::len_number("len(1)", "RuntimeError: len(): Need a string or list argument")]
+#[case::substring_range("substring(\"abc\", 2, 4)", "RuntimeError: substring(): Index out of range")]
+#[case::substring_fraction("\"abc\".substring(0.5, 1)", "RuntimeError: substring(): Need a non-negative integer index")]
+#[case::split_number("split(1, \",\")", "RuntimeError: split(): Need a string argument")]
+#[case::join_string("join(\"abc\", \",\")", "RuntimeError: join(): Need a list argument")]
+#[case::ord_long("ord(\"ab\")", "RuntimeError: ord(): Need a single character")]
+#[case::chr_surrogate("chr(55296)", "RuntimeError: chr(): Invalid code point")]
+#[case::method_arity("\"abc\".charAt()", "RuntimeError: Expected 1 arguments but got 0.")]
+fn test_string_errors(#[case] source: &str, #[case] expected: &str) {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let expression = parser.parse_expr().unwrap();
+    let mut interpreter = Interpreter::new();
+    let error = expression.accept(&mut interpreter).unwrap_err();
+    assert_eq!(error.to_string(), expected);
+}
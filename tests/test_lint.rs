@@ -0,0 +1,146 @@
+//! This file will test the warnings of the Resolver, `lox-allow` comments, strict mode and arity checks
+
+use rlox::interpreter::Interpreter;
+use rlox::lint::{strict_pragma, Allows, Lint, Warning};
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+use rstest::rstest;
+
+fn lint(source: &str) -> Vec<Warning> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse().unwrap();
+
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::new(&mut interpreter);
+    resolver.silent = true;
+    resolver.allows = Allows::from_source(source);
+    resolver.resolve_program(&program);
+    assert!(!resolver.had_error);
+    resolver.warnings
+}
+
+#[rstest]
+#[case::unused_variable("{ var a = 1; }", Lint::UnusedVariable, 1)]
+#[case::unused_parameter("fun f(a) { return 1; }\nprint f(1);", Lint::UnusedParameter, 1)]
+#[case::unreachable_code("fun f() {\n  return 1;\n  print 2;\n}\nprint f();", Lint::UnreachableCode, 3)]
+#[case::shadowed_local("{ var a = 1;\n  { var a = 2; print a; }\n  print a; }", Lint::ShadowedLocal, 2)]
+#[case::assign_to_function("fun f() {}\nf = 1;", Lint::AssignToDeclaration, 2)]
+#[case::assign_to_class("{ class A {}\n  A = nil;\n  print A; }", Lint::AssignToDeclaration, 2)]
+#[case::self_comparison("var a = 1;\nprint a == a;", Lint::SelfComparison, 2)]
+#[case::self_comparison_property("var a;\nprint a.b < a.b;", Lint::SelfComparison, 2)]
+#[case::constant_condition("if (true) print 1;", Lint::ConstantCondition, 1)]
+#[case::constant_condition_grouped("if ((nil)) print 1;", Lint::ConstantCondition, 1)]
+#[case::empty_block("while (false) {}", Lint::EmptyBlock, 1)]
+fn test_lint(#[case] source: &str, #[case] expected: Lint, #[case] line: usize) {
+    let warnings = lint(source);
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert_eq!(warnings[0].lint, expected);
+    assert_eq!(warnings[0].line, line);
+}
+
+#[rstest]
+#[case::used_variable("{ var a = 1; print a; }")]
+#[case::underscore_parameter("fun f(_a) { return 1; }\nprint f(1);")]
+#[case::globals_are_not_unused("var a = 1;")]
+#[case::shadowed_global("var a = 1;\n{ var a = 2; print a; }")]
+#[case::different_comparison("var a = 1; var b = 2;\nprint a == b;")]
+#[case::variable_condition("var a = true;\nif (a) print 1;")]
+#[case::empty_function("fun f() {}\nf();")]
+fn test_no_lint(#[case] source: &str) {
+    assert_eq!(lint(source), vec![]);
+}
+
+#[rstest]
+#[case::whole_file("// lox-allow: unused-variable\n{ var a = 1; }\n{ var b = 2; }", 0)]
+#[case::trailing("{ var a = 1; } // lox-allow: unused-variable\n{ var b = 2; }", 1)]
+#[case::next_line("print 1;\n// lox-allow: unused-variable\n{ var a = 1; }\n{ var b = 2; }", 1)]
+#[case::several_codes("// lox-allow: empty-block, constant-condition\nif (true) {}", 0)]
+#[case::other_code("// lox-allow: empty-block\n{ var a = 1; }", 1)]
+#[case::unknown_code("// lox-allow: unused\n{ var a = 1; }", 1)]
+fn test_allow(#[case] source: &str, #[case] count: usize) {
+    assert_eq!(lint(source).len(), count);
+}
+
+/// Resolves `source` in strict mode and returns the error messages.
+fn strict(source: &str) -> Vec<String> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse().unwrap();
+
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::new(&mut interpreter);
+    resolver.silent = true;
+    resolver.strict = true;
+    resolver.resolve_program(&program);
+    resolver.errors.iter().map(|error| error.message()).collect()
+}
+
+#[rstest]
+#[case::declared_later("fun f() { return g(); }\nfun g() { return 1; }\nprint f();", &[])]
+#[case::builtins("print clock() + PI + sqrt(4);", &[])]
+#[case::locals("fun f(a) { var b = a; return b; }\nprint f(1);", &[])]
+#[case::undefined("print a;", &["Undefined variable 'a'."])]
+#[case::assignment("a = 1;", &["Undefined variable 'a'."])]
+#[case::typo("var total = 1;\nprint totl;", &["Undefined variable 'totl'. Did you mean 'total'?"])]
+#[case::swapped_chars("fun f(count) { return cuont; }\nprint f(1);", &["Undefined variable 'cuont'. Did you mean 'count'?"])]
+#[case::builtin_typo("print sqtr(4);", &["Undefined variable 'sqtr'. Did you mean 'sqrt'?"])]
+#[case::no_suggestion_for_short_names("var b = 1;\nprint a;", &["Undefined variable 'a'."])]
+#[case::local_out_of_scope("{ var inner = 1; }\nprint inner;", &["Undefined variable 'inner'."])]
+fn test_strict(#[case] source: &str, #[case] expected: &[&str]) {
+    assert_eq!(strict(source), expected);
+}
+
+/// Resolves `source` and returns the error messages.
+fn resolve_errors(source: &str) -> Vec<String> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse().unwrap();
+
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::new(&mut interpreter);
+    resolver.silent = true;
+    resolver.resolve_program(&program);
+    resolver.errors.iter().map(|error| error.message()).collect()
+}
+
+#[rstest]
+#[case::function("fun f(a, b) {}\nf(1);", &["Expected 2 arguments but got 1."])]
+#[case::declared_later("fun g() { f(1, 2, 3); }\nfun f(a, b) {}", &["Expected 2 arguments but got 3."])]
+#[case::local_function("{ fun f(a) {}\n  f(); }", &["Expected 1 arguments but got 0."])]
+#[case::class_init("class P { init(x, y) {} }\nP(1);", &["Expected 2 arguments but got 1."])]
+#[case::inherited_init("class P { init(x) {} }\nclass Q < P {}\nQ();", &["Expected 1 arguments but got 0."])]
+#[case::builtin("print sqrt(1, 2);", &["Expected 1 arguments but got 2."])]
+#[case::matching("fun f(a) {}\nf(1); print clock();", &[])]
+#[case::class_without_init("class A {}\nA(1, 2);", &[])]
+#[case::reassigned("fun f(a) {}\nfun g() {}\nf = g;\nf();", &[])]
+#[case::reassigned_in_function("fun f(a) {}\nfun g() { f = clock; }\nf();", &[])]
+#[case::shadowed_by_variable("fun f(a) {}\n{ var f = clock;\n  f(); }", &[])]
+#[case::shadowed_by_parameter("fun f(a) {}\nfun g(f) { f(); }", &[])]
+#[case::shadowed_builtin("var sqrt = clock;\nsqrt();", &[])]
+#[case::method("class A { m(x) {} }\nA().m();", &[])]
+fn test_arity(#[case] source: &str, #[case] expected: &[&str]) {
+    assert_eq!(resolve_errors(source), expected);
+}
+
+#[rstest]
+#[case("// lox-strict\nprint 1;", true)]
+#[case("/// docs\n// lox-strict\nprint 1;", true)]
+#[case("print 1; // lox-strict", false)]
+#[case("print 1;", false)]
+fn test_strict_pragma(#[case] source: &str, #[case] expected: bool) {
+    assert_eq!(strict_pragma(source), expected);
+}
+
+#[test]
+fn test_codes() {
+    for lint in Lint::ALL {
+        assert_eq!(Lint::from_code(lint.code()), Some(lint));
+    }
+    let warning = Warning { lint: Lint::EmptyBlock, line: 3, message: "Empty block.".to_string() };
+    assert_eq!(warning.to_string(), "Warning at line 3: Empty block. [empty-block]");
+}
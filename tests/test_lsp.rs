@@ -0,0 +1,229 @@
+//! This file will test the language server with scripted JSON-RPC sessions
+
+use std::io::Cursor;
+use serde_json::{json, Value};
+
+use rlox::lsp::run_server;
+use rlox::rpc::{read_message, write_message};
+
+const URI: &str = "file:///test.lox";
+
+const SOURCE: &str = "\
+class Animal {
+  init(name) { this.name = name; }
+  speak(times) { print this.name; }
+}
+fun greet(a, b) {
+  var local = a;
+  return local + b;
+}
+var pet = Animal(\"cat\");
+pet.speak(1);
+print greet(1, 2);
+";
+
+/// Feeds the messages to a fresh server and returns everything it wrote back.
+fn session(messages: Vec<Value>) -> Vec<Value> {
+    let mut input = vec![];
+    for message in &messages {
+        write_message(&mut input, message).unwrap();
+    }
+    let mut output = vec![];
+    run_server(Cursor::new(input), &mut output).unwrap();
+
+    let mut replies = vec![];
+    let mut reader = Cursor::new(output);
+    while let Some(reply) = read_message(&mut reader).unwrap() {
+        replies.push(reply);
+    }
+    replies
+}
+
+fn open(text: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": URI, "languageId": "lox", "version": 1, "text": text } },
+    })
+}
+
+fn request(id: u64, method: &str, line: u64, character: u64) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": {
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+            "context": { "includeDeclaration": true },
+        },
+    })
+}
+
+fn result_of(replies: &[Value], id: u64) -> Value {
+    replies.iter()
+        .find(|reply| reply["id"] == json!(id))
+        .map(|reply| reply["result"].clone())
+        .expect("missing reply")
+}
+
+#[test]
+fn test_initialize_and_shutdown() {
+    let replies = session(vec![
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+    ]);
+    assert_eq!(replies.len(), 2, "nothing is handled after exit");
+    let capabilities = &result_of(&replies, 1)["capabilities"];
+    assert_eq!(capabilities["definitionProvider"], json!(true));
+    assert_eq!(capabilities["documentSymbolProvider"], json!(true));
+    assert_eq!(result_of(&replies, 2), Value::Null);
+}
+
+#[test]
+fn test_diagnostics() {
+    let replies = session(vec![open("var a = 1;\nprint a +;\nreturn 1;\n")]);
+    assert_eq!(replies[0]["method"], json!("textDocument/publishDiagnostics"));
+    let diagnostics = replies[0]["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 1, "character": 9 }));
+    assert!(diagnostics[0]["message"].as_str().unwrap().contains("Expected expression"));
+    assert_eq!(diagnostics[1]["range"]["start"]["line"], json!(2));
+    assert!(diagnostics[1]["message"].as_str().unwrap().contains("Can't return from top-level code."));
+
+    assert_eq!(diagnostics[0]["severity"], json!(1));
+
+    // The only problem in SOURCE is the unused parameter of `speak`, a warning.
+    let replies = session(vec![open(SOURCE)]);
+    let diagnostics = replies[0]["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["severity"], json!(2));
+    assert_eq!(diagnostics[0]["code"], json!("unused-parameter"));
+    assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 2, "character": 2 }));
+
+    let replies = session(vec![open("// lox-allow: unused-parameter\nfun f(a) {}\n")]);
+    assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+}
+
+#[test]
+fn test_definition() {
+    let replies = session(vec![
+        open(SOURCE),
+        // `greet` in `print greet(1, 2);`
+        request(1, "textDocument/definition", 10, 7),
+        // `local` in `return local + b;`
+        request(2, "textDocument/definition", 6, 10),
+        // `speak` in `pet.speak(1);`
+        request(3, "textDocument/definition", 9, 5),
+        // `Animal` in `var pet = Animal("cat");`
+        request(4, "textDocument/definition", 8, 11),
+        // nothing at an operator
+        request(5, "textDocument/definition", 6, 16),
+    ]);
+    assert_eq!(result_of(&replies, 1)["range"]["start"], json!({ "line": 4, "character": 4 }));
+    assert_eq!(result_of(&replies, 2)["range"]["start"], json!({ "line": 5, "character": 6 }));
+    assert_eq!(result_of(&replies, 3)["range"]["start"], json!({ "line": 2, "character": 2 }));
+    assert_eq!(result_of(&replies, 4)["range"]["start"], json!({ "line": 0, "character": 6 }));
+    assert_eq!(result_of(&replies, 5), Value::Null);
+}
+
+#[test]
+fn test_unicode_identifiers() {
+    let replies = session(vec![
+        open("var größe = 1;\nprint größe + größe;\n"),
+        // the last character of the first `größe` on the second line
+        request(1, "textDocument/definition", 1, 10),
+        // the `+` right after it
+        request(2, "textDocument/definition", 1, 12),
+        request(3, "textDocument/references", 0, 4),
+    ]);
+    assert_eq!(result_of(&replies, 1)["range"], json!({
+        "start": { "line": 0, "character": 4 },
+        "end": { "line": 0, "character": 9 },
+    }));
+    assert_eq!(result_of(&replies, 2), Value::Null);
+    let ends: Vec<Value> = result_of(&replies, 3).as_array().unwrap().iter()
+        .map(|location| location["range"]["end"].clone())
+        .collect();
+    assert_eq!(ends, vec![
+        json!({ "line": 0, "character": 9 }),
+        json!({ "line": 1, "character": 11 }),
+        json!({ "line": 1, "character": 19 }),
+    ]);
+}
+
+#[test]
+fn test_references() {
+    let replies = session(vec![
+        open(SOURCE),
+        request(1, "textDocument/references", 5, 6),
+        request(2, "textDocument/references", 4, 13),
+    ]);
+    let local: Vec<u64> = result_of(&replies, 1).as_array().unwrap().iter()
+        .map(|location| location["range"]["start"]["line"].as_u64().unwrap())
+        .collect();
+    assert_eq!(local, vec![5, 6]);
+    // parameter `b` of `greet`
+    assert_eq!(result_of(&replies, 2).as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_hover() {
+    let replies = session(vec![
+        open(SOURCE),
+        request(1, "textDocument/hover", 10, 8),
+        request(2, "textDocument/hover", 8, 12),
+    ]);
+    let greet = result_of(&replies, 1)["contents"]["value"].as_str().unwrap().to_string();
+    assert!(greet.contains("fun greet(a, b)"), "{}", greet);
+    assert!(greet.contains("Takes 2 arguments."), "{}", greet);
+    let animal = result_of(&replies, 2)["contents"]["value"].as_str().unwrap().to_string();
+    assert!(animal.contains("Constructor takes 1 argument."), "{}", animal);
+}
+
+#[test]
+fn test_completion() {
+    let replies = session(vec![
+        open(SOURCE),
+        request(1, "textDocument/completion", 6, 0),
+        request(2, "textDocument/completion", 10, 0),
+    ]);
+    let labels = |id| -> Vec<String> {
+        result_of(&replies, id).as_array().unwrap().iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let inside = labels(1);
+    for name in ["local", "a", "b", "greet", "pet", "Animal", "clock", "while"] {
+        assert!(inside.contains(&name.to_string()), "missing {}", name);
+    }
+    let outside = labels(2);
+    assert!(!outside.contains(&"local".to_string()));
+    assert!(outside.contains(&"greet".to_string()));
+}
+
+#[test]
+fn test_document_symbols() {
+    let replies = session(vec![
+        open(SOURCE),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "textDocument/documentSymbol",
+            "params": { "textDocument": { "uri": URI } },
+        }),
+    ]);
+    let symbols: Vec<(String, Option<String>)> = result_of(&replies, 1).as_array().unwrap().iter()
+        .map(|s| (s["name"].as_str().unwrap().to_string(), s["containerName"].as_str().map(str::to_string)))
+        .collect();
+    assert_eq!(symbols, vec![
+        ("Animal".to_string(), None),
+        ("init".to_string(), Some("Animal".to_string())),
+        ("speak".to_string(), Some("Animal".to_string())),
+        ("greet".to_string(), None),
+        ("pet".to_string(), None),
+    ]);
+}
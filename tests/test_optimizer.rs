@@ -0,0 +1,59 @@
+//! This file will test the Optimizer, comparing optimized programs in their printed form
+
+use rlox::ast::pretty_printer;
+use rlox::optimizer::Optimizer;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rstest::rstest;
+
+fn optimize(source: &str) -> String {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse().unwrap();
+    let program = Optimizer::new().optimize(program);
+    let mut printer = pretty_printer::AstPrinter();
+    program.accept(&mut printer)
+}
+
+#[rstest]
+#[case::arithmetic("print 1 + 2 * 3;", "[(print 7)]")]
+#[case::nested_groups("print (1 + 2) * (10 - 4) / 3;", "[(print 6)]")]
+#[case::modulo("print 7 % 4;", "[(print 3)]")]
+#[case::concatenation("print \"a\" + \"b\" + \"c\";", "[(print abc)]")]
+#[case::interpolation("print \"${1 + 1} apples\";", "[(print 2 apples)]")]
+#[case::comparison("print 1 < 2 == true;", "[(print true)]")]
+#[case::not("print !true; print !nil;", "[(print false);(print true)]")]
+#[case::negate("print -(2 + 3);", "[(print -5)]")]
+#[case::and("print true and x; print nil and x;", "[(print x);(print nil)]")]
+#[case::or("print \"a\" or x; print false or x;", "[(print a);(print x)]")]
+#[case::partial("print x + (1 + 2);", "[(print (+ x 3))]")]
+#[case::inside_calls("f(2 * 2).g = 1 + 1;", "[(set (call f 4) g 2)]")]
+fn test_folding(#[case] source: &str, #[case] expected: &str) {
+    assert_eq!(optimize(source), expected);
+}
+
+#[rstest]
+#[case::division_by_zero("print 1 / 0;", "[(print (/ 1 0))]")]
+#[case::modulo_by_zero("print 1 % 0;", "[(print (% 1 0))]")]
+#[case::mixed_operands("print \"hi\" - 1;", "[(print (- hi 1))]")]
+#[case::negate_string("print -\"a\";", "[(print (- a))]")]
+#[case::inner_folded("print 1 / (2 - 2);", "[(print (/ 1 0))]")]
+fn test_errors_kept(#[case] source: &str, #[case] expected: &str) {
+    assert_eq!(optimize(source), expected);
+}
+
+#[rstest]
+#[case::if_false("if (false) print 1; print 2;", "[(print 2)]")]
+#[case::if_false_else("if (nil) print 1; else print 2;", "[(print 2)]")]
+#[case::if_true("if (1 < 2) print 1; else print 2;", "[(print 1)]")]
+#[case::if_kept("if (x) print 1; else if (false) print 2;", "[(if x (print 1))]")]
+#[case::while_false("while (false) print 1; print 2;", "[(print 2)]")]
+#[case::while_kept("while (x) if (false) print 1;", "[(while x [])]")]
+#[case::after_return("fun f() { return 1; print 2; }", "[(function f () [(return 1)])]")]
+#[case::after_returning_if("fun f() { if (x) return 1; else return 2; print 3; }", "[(function f () [(if x (return 1) (return 2))])]")]
+#[case::after_returning_block("fun f() { { return 1; } print 2; }", "[(function f () [[(return 1)]])]")]
+#[case::conditional_return("fun f() { if (x) return 1; print 2; }", "[(function f () [(if x (return 1));(print 2)])]")]
+fn test_pruning(#[case] source: &str, #[case] expected: &str) {
+    assert_eq!(optimize(source), expected);
+}
@@ -1,6 +1,8 @@
 //! This file will test Scanner and Parser modules
 
 use rlox::ast::pretty_printer;
+use rlox::ast::expr::{Expr, NodeId};
+use rlox::ast::stmt::Stmt;
 use rlox::scanner::Scanner;
 use rlox::parser::Parser;
 use rstest::rstest;
@@ -24,6 +26,17 @@ use rstest::rstest;
 #[case("(a or b) and c;", "[(and (group (or a b)) c)]")]
 #[case("a or b and c or d;", "[(or (or a (and b c)) d)]")]
 #[case("a and !b;", "[(and a (! b))]")]
+#[case::interpolation("\"x = ${x + 1}!\";", "[(interpolate x =  (+ x 1) !)]")]
+#[case::interpolation_only("\"${a}${b}\";", "[(interpolate a b)]")]
+#[case::interpolation_nested("\"a${\"b${c}\"}\";", "[(interpolate a (interpolate b c))]")]
+#[case::interpolation_escaped("\"\\${x}\";", "[${x}]")]
+#[case::unicode_identifier("größe * π;", "[(* größe π)]")]
+#[case::unicode_escape("\"caf\\u{e9} \\u{1F600}\";", "[café 😀]")]
+#[case::legacy_escapes("\"\\x41\\u0042\\103\\60\";", "[ABC0]")]
+#[case::hex("0xFF + 0X1f;", "[(+ 255 31)]")]
+#[case::binary_and_octal("0b1010 + 0o17;", "[(+ 10 15)]")]
+#[case::exponent("1e-9 + 6.02E23 + 2e+3;", "[(+ (+ 0.000000001 602000000000000000000000) 2000)]")]
+#[case::separators("1_000_000 + 0xFF_FF + 1_0.5_5;", "[(+ (+ 1000000 65535) 10.55)]")]
 fn test_expr_stmt(#[case] source: &str, #[case] expected: &str) {
     let mut scanner = Scanner::new(source.to_string());
     let tokens = scanner.scan_tokens();
@@ -39,6 +52,9 @@ fn test_expr_stmt(#[case] source: &str, #[case] expected: &str) {
 #[case("var a = 1 + 2;", "[(var a = (+ 1 2))]")]
 #[case("print 1 + 2;", "[(print (+ 1 2))]")]
 #[case("var a; print a;", "[(var a);(print a)]")]
+#[case::test_decl("test \"adds\" { print 1 + 2; }", "[(test \"adds\" [(print (+ 1 2))])]")]
+#[case::test_as_identifier("var test = 1; test = 2;", "[(var test = 1);(= test 2)]")]
+#[case::annotated_var("var a: Number = 1 + 2;", "[(var a = (+ 1 2))]")]
 fn test_simple_stmt(#[case] source: &str, #[case] expected: &str) {
     let mut scanner = Scanner::new(source.to_string());
     let tokens = scanner.scan_tokens();
@@ -46,4 +62,136 @@ fn test_simple_stmt(#[case] source: &str, #[case] expected: &str) {
     let statement = parser.parse().unwrap();
     let mut printer = pretty_printer::AstPrinter();
     assert_eq!(statement.accept(&mut printer), expected);
-}
\ No newline at end of file
+}
+
+#[rstest]
+#[case::identifier("var π = 1;", vec![1, 5, 7, 9, 10, 11])]
+#[case::string("\"héllo\" + ñ;", vec![1, 9, 11, 12, 13])]
+#[case::comment("// ünïcödé\n  a;", vec![3, 4, 5])]
+fn test_scan_columns(#[case] source: &str, #[case] expected: Vec<usize>) {
+    let mut scanner = Scanner::new(source.to_string());
+    let columns: Vec<usize> = scanner.scan_tokens().iter().map(|token| token.column).collect();
+    assert!(!scanner.had_error);
+    assert_eq!(columns, expected);
+}
+
+#[rstest]
+#[case::unknown_escape("\"\\q\";", "\\q")]
+#[case::invalid_scalar("\"\\u{D800}\";", "\\u{D800}")]
+#[case::unclosed_unicode_escape("\"\\u{41\";", "\\u{41")]
+#[case::short_hex_escape("\"\\x4\";", "\\x4")]
+#[case::short_unicode_escape("\"\\u00e\";", "\\u00e")]
+#[case::lone_octal_digit("\"\\7\";", "\\7")]
+fn test_invalid_escape(#[case] source: &str, #[case] near: &str) {
+    let mut scanner = Scanner::new(source.to_string());
+    let mut parser = Parser::new(scanner.scan_tokens());
+    parser.silent = true;
+    let _ = parser.parse();
+    assert_eq!(parser.errors.len(), 1);
+    assert_eq!(parser.errors[0].near(), Some(near));
+}
+
+#[rstest]
+#[case::empty_hex("0x")]
+#[case::bad_hex_digit("0xFG")]
+#[case::bad_binary_digit("0b102")]
+#[case::trailing_separator("1_000_")]
+#[case::double_separator("1__000")]
+#[case::separator_after_prefix("0x_FF")]
+#[case::empty_exponent("1e")]
+#[case::signed_empty_exponent("2.5E-")]
+fn test_invalid_number(#[case] literal: &str) {
+    let mut scanner = Scanner::new(format!("print {};", literal));
+    scanner.silent = true;
+    scanner.scan_tokens();
+    assert_eq!(scanner.errors.len(), 1);
+    assert_eq!(scanner.errors[0].to_string(), format!("Error at '{}': Invalid number.", literal));
+}
+
+#[test]
+fn test_doc_comments() {
+    let source = "\
+/// Adds two numbers.
+///   Indented line.
+fun add(a, b) { return a + b; }
+//// Not a doc comment.
+fun plain() {}
+/// Dropped, as no declaration follows.
+var x = 1;
+/// A point.
+class Point {
+  /// Makes one.
+  init(x) { this.x = x; }
+  /* block */ len() { return 0; }
+}
+";
+    let mut scanner = Scanner::new(source.to_string());
+    let mut parser = Parser::new(scanner.scan_tokens());
+    let Some(Stmt::Program(stmts)) = parser.parse() else { panic!("expected a program") };
+    let docs: Vec<Option<&str>> = stmts.iter().map(Stmt::doc).collect();
+    assert_eq!(docs, vec![Some("Adds two numbers.\n  Indented line."), None, None, Some("A point.")]);
+    let Stmt::ClassDecl(_, _, methods, _) = &stmts[3] else { panic!("expected a class") };
+    let method_docs: Vec<Option<&str>> = methods.iter().map(Stmt::doc).collect();
+    assert_eq!(method_docs, vec![Some("Makes one."), None]);
+}
+
+#[rstest]
+#[case::block("1 /* two */ + 3;", "[(+ 1 3)]")]
+#[case::nested("1 /* a /* b */ c */ + 3;", "[(+ 1 3)]")]
+#[case::multi_line("/* line\n ** line */ 1;", "[1]")]
+#[case::doc_before_statement("/// stray\n1;", "[1]")]
+fn test_block_comment(#[case] source: &str, #[case] expected: &str) {
+    let mut scanner = Scanner::new(source.to_string());
+    let mut parser = Parser::new(scanner.scan_tokens());
+    let statement = parser.parse().unwrap();
+    assert!(!scanner.had_error);
+    assert_eq!(statement.accept(&mut pretty_printer::AstPrinter()), expected);
+}
+
+#[rstest]
+#[case::unclosed("1; /* never closed")]
+#[case::unclosed_nested("/* outer /* inner */ still open\n1;")]
+fn test_unterminated_block_comment(#[case] source: &str) {
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.silent = true;
+    scanner.scan_tokens();
+    assert_eq!(scanner.errors.len(), 1);
+    assert_eq!(scanner.errors[0].to_string(), "Error at '/*': Unterminated block comment.");
+    assert_eq!(scanner.errors[0].line(), Some(1));
+}
+
+#[test]
+fn test_type_annotations() {
+    let source = "fun add(a: Number, b): Number { return a + b; }\nvar name: String;\nfun plain(x) {}\n";
+    let mut scanner = Scanner::new(source.to_string());
+    let mut parser = Parser::new(scanner.scan_tokens());
+    let Some(Stmt::Program(stmts)) = parser.parse() else { panic!("expected a program") };
+    let Stmt::FunctionDecl(_, _, _, _, signature) = &stmts[0] else { panic!("expected a function") };
+    let params: Vec<Option<&str>> = signature.params.iter().map(|p| p.as_ref().map(|t| t.lexeme.as_str())).collect();
+    assert_eq!(params, vec![Some("Number"), None]);
+    assert_eq!(signature.returns.as_ref().map(|t| t.lexeme.as_str()), Some("Number"));
+    let Stmt::Var(_, None, Some(annotation)) = &stmts[1] else { panic!("expected an annotated variable") };
+    assert_eq!(annotation.lexeme, "String");
+    let Stmt::FunctionDecl(_, _, _, _, signature) = &stmts[2] else { panic!("expected a function") };
+    assert!(signature.params == vec![None] && signature.returns.is_none());
+
+    let mut scanner = Scanner::new("var a: = 1;".to_string());
+    let mut parser = Parser::new(scanner.scan_tokens());
+    parser.silent = true;
+    parser.parse();
+    assert_eq!(parser.errors[0].message(), "Expect type name after ':'.");
+}
+
+#[test]
+fn test_node_ids() {
+    let mut scanner = Scanner::new("a = b;\nthis;".to_string());
+    let mut parser = Parser::new(scanner.scan_tokens());
+    parser.next_id = 5;
+    let Some(Stmt::Program(stmts)) = parser.parse() else { panic!("expected a program") };
+    let Stmt::Expression(Expr::Assign(_, value, assign_id), _) = &stmts[0] else { panic!("expected an assignment") };
+    let Expr::Variable(_, variable_id) = **value else { panic!("expected a variable") };
+    let Stmt::Expression(Expr::This(_, this_id), _) = &stmts[1] else { panic!("expected this") };
+    // the assignment keeps the id of the variable it was parsed as
+    assert_eq!((*assign_id, variable_id, *this_id), (NodeId(5), NodeId(6), NodeId(7)));
+    assert_eq!(parser.next_id, 8);
+}
@@ -0,0 +1,109 @@
+//! This file will test the profiler's call counts and call stacks
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::profiler::Profiler;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+
+const SOURCE: &str = "\
+fun fib(n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+class Timer {
+  init() { this.started = clock(); }
+}
+Timer();
+fib(4);
+fun fail() { return nil + 1; }
+fail();
+";
+
+fn profile(source: &str) -> Profiler {
+    let mut interpreter = Interpreter::new();
+    interpreter.output = Box::new(std::io::sink());
+    let tokens = Scanner::new(source.to_string()).scan_tokens();
+    let program = Parser::new(tokens).parse().unwrap();
+    Resolver::new(&mut interpreter).resolve_program(&program);
+
+    let profiler = Rc::new(RefCell::new(Profiler::new()));
+    interpreter.hook = Some(Box::new(Rc::clone(&profiler)));
+    interpreter.interpret(program);
+    interpreter.hook = None;
+
+    let mut profiler = Rc::try_unwrap(profiler).unwrap().into_inner();
+    profiler.finish();
+    profiler
+}
+
+#[test]
+fn test_call_counts() {
+    let profiler = profile(SOURCE);
+    assert_eq!(profiler.functions["fib"].calls, 9);
+    assert_eq!(profiler.functions["Timer.init"].calls, 1);
+    assert_eq!(profiler.functions["clock"].calls, 1);
+    // a call that fails with a runtime error still returns
+    assert_eq!(profiler.functions["fail"].calls, 1);
+
+    let fib = &profiler.functions["fib"];
+    assert_eq!(fib.inclusive, fib.exclusive, "fib only calls itself");
+    let script = &profiler.functions["<script>"];
+    assert!(script.inclusive >= fib.inclusive);
+}
+
+#[test]
+fn test_collapsed_stacks() {
+    let profiler = profile(SOURCE);
+    let mut stacks: Vec<&str> = profiler.stacks.keys().map(String::as_str).collect();
+    stacks.sort();
+    assert_eq!(stacks, vec![
+        "<script>",
+        "<script>;Timer.init",
+        "<script>;Timer.init;clock",
+        "<script>;fail",
+        "<script>;fib",
+        "<script>;fib;fib",
+        "<script>;fib;fib;fib",
+        "<script>;fib;fib;fib;fib",
+    ]);
+
+    let mut folded = vec![];
+    profiler.write_folded(&mut folded).unwrap();
+    let folded = String::from_utf8(folded).unwrap();
+    assert_eq!(folded.lines().count(), stacks.len());
+    assert!(folded.lines().all(|line| line.rsplit_once(' ').unwrap().1.parse::<u128>().is_ok()));
+
+    let mut summary = vec![];
+    profiler.write_summary(&mut summary).unwrap();
+    let summary = String::from_utf8(summary).unwrap();
+    assert!(summary.starts_with("function"));
+    assert!(summary.contains("fib") && summary.contains("clock"));
+    assert!(!summary.contains("<script>"));
+}
+
+#[test]
+fn test_methods_by_class() {
+    let profiler = profile("\
+class A {
+  init() {}
+  name() { return \"a\"; }
+}
+class B < A {
+  init() { super.init(); }
+  name() { return super.name() + \"b\"; }
+}
+A().name();
+B().name();
+B().name();
+");
+    assert_eq!(profiler.functions["A.init"].calls, 3);
+    assert_eq!(profiler.functions["B.init"].calls, 2);
+    assert_eq!(profiler.functions["A.name"].calls, 3);
+    assert_eq!(profiler.functions["B.name"].calls, 2);
+    assert!(profiler.stacks.contains_key("<script>;B.name;A.name"));
+    assert!(!profiler.functions.contains_key("init"));
+}
@@ -0,0 +1,106 @@
+//! This file will test the `rlox test` runner: annotations, diffs and blessing
+
+use std::path::PathBuf;
+
+use rlox::testing::{bless, check, diff, parse_expectations, run_source, run_suite};
+
+#[test]
+fn test_annotated_scripts() {
+    let mut report = vec![];
+    let passed = run_suite(&[PathBuf::from("tests/expect")], false, &mut report).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    assert!(passed, "{}", report);
+    assert!(report.ends_with("\n16 passed, 0 failed\n"), "{}", report);
+    assert!(report.contains("PASS tests/expect/unit_tests.lox (3 tests)"), "{}", report);
+}
+
+#[test]
+fn test_parse_expectations() {
+    let expected = parse_expectations("\
+print 1; // expect: 1 // expect: two words
+var a = ; // error at ';': Expected expression.
+// a plain comment
+f(); // expect runtime error: Boom.
+");
+    assert_eq!(expected.output, vec![(1, "1".to_string()), (1, "two words".to_string())]);
+    assert_eq!(expected.errors, vec![
+        (2, "error at ';': Expected expression.".to_string()),
+        (4, "runtime error: Boom.".to_string()),
+    ]);
+}
+
+#[test]
+fn test_failure_report() {
+    let report = check("print 1; // expect: 1\nprint 3; // expect: 2\nprint nil + 1;\n").unwrap_err();
+    assert_eq!(report, vec![
+        "output:",
+        "  1",
+        "- 2",
+        "+ 3",
+        "errors:",
+        "+ [line 3] runtime error: Operands must be two numbers or two strings.",
+    ]);
+}
+
+#[test]
+fn test_declared_tests() {
+    let source = "\
+fun check(x) {
+  assert(x > 1, \"x should be big\");
+}
+test \"too small\" {
+  check(0);
+}
+test \"different\" {
+  assertEqual(1 + 1, \"2\");
+}
+test \"passes\" {
+  assertEqual(1 + 1, 2);
+}
+";
+    let outcome = run_source(source);
+    assert_eq!(outcome.tests, vec![
+        ("too small".to_string(), Err((2, "assert(): x should be big".to_string()))),
+        ("different".to_string(), Err((8, "assertEqual(): 2 is not equal to 2".to_string()))),
+        ("passes".to_string(), Ok(())),
+    ]);
+    assert_eq!(check(source).unwrap_err(), vec![
+        "tests:",
+        "test \"too small\" failed at line 2: assert(): x should be big",
+        "test \"different\" failed at line 8: assertEqual(): 2 is not equal to 2",
+    ]);
+    // tests don't run when the script itself fails
+    assert!(run_source("test \"t\" { } print nil + 1;").tests.is_empty());
+}
+
+#[test]
+fn test_diff() {
+    let lines = |text: &str| text.split(' ').map(str::to_string).collect::<Vec<_>>();
+    assert_eq!(diff(&lines("a b c"), &lines("a c d")), vec!["  a", "- b", "  c", "+ d"]);
+    assert!(diff(&[], &[]).is_empty());
+}
+
+#[test]
+fn test_bless() {
+    let source = "\
+var i = 0; // expect: stale
+while (i < 2) {
+  print i;
+  i = i + 1;
+}
+// expect: stale too
+print \"done\"; // not an annotation
+print nil + 1;
+";
+    let blessed = bless(source, &run_source(source));
+    assert_eq!(blessed, "\
+var i = 0;
+while (i < 2) {
+  print i; // expect: 0 // expect: 1
+  i = i + 1;
+}
+print \"done\"; // not an annotation // expect: done
+print nil + 1; // expect runtime error: Operands must be two numbers or two strings.
+");
+    assert_eq!(check(&blessed), Ok(0));
+}